@@ -0,0 +1,810 @@
+// The static checker run by slash check. It walks the parse tree of a script and its includes without
+// executing anything, infers the types of expressions where they are known, and reports every place where
+// a known type contradicts a type annotation, the parameters of a function or an operator. A type that
+// cannot be known without running the script is Any, which is never reported.
+
+use pest::iterators::Pair;
+use pest::Parser;
+use pest::Span;
+use crate::{Rule, SlashParser};
+use crate::error::SlashError;
+use crate::evaluate::{Node, climb, flatten_arguments, is_method};
+use crate::function::{signature, annotated_type, Signature};
+use crate::generator::contains_yield;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::fs;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Any,
+    Number,
+    String,
+    List,
+    Table,
+    Function,
+    ProcessResult,
+    Iterator,
+    Record(String),
+    Regex,
+    Null,
+}
+
+impl Type {
+    fn name(&self) -> &str {
+        match self {
+            Type::Any => "Any",
+            Type::Number => "Number",
+            Type::String => "String",
+            Type::List => "List",
+            Type::Table => "Table",
+            Type::Function => "Function",
+            Type::ProcessResult => "ProcessResult",
+            Type::Iterator => "Iterator",
+            Type::Record(name) => name,
+            Type::Regex => "Regex",
+            Type::Null => "Null",
+        }
+    }
+
+    fn is_known(&self) -> bool {
+        *self != Type::Any
+    }
+
+    // Whether a value of type actual may be used where expected is required
+    fn accepts(&self, actual: &Type) -> bool {
+        !self.is_known() || !actual.is_known() || self == actual
+    }
+
+    // The common type of the values of branches, Any if they differ
+    fn unify(types: Vec<Type>) -> Type {
+        match types.first() {
+            Some(first) if types.iter().all(|t| t == first) => first.clone(),
+            _ => Type::Any
+        }
+    }
+}
+
+struct Param {
+    name: String,
+    ty: Type,
+    optional: bool,
+    rest: bool,
+}
+
+struct FunctionType {
+    params: Vec<Param>,
+    returns: Type,
+    named: bool,
+}
+
+// The inferred type of an expression, and the type of the function if the expression is a known function
+#[derive(Clone)]
+struct Inferred {
+    ty: Type,
+    function: Option<Rc<FunctionType>>,
+}
+
+impl Inferred {
+    fn of(ty: Type) -> Inferred {
+        Inferred { ty, function: None }
+    }
+}
+
+#[derive(Clone)]
+struct Binding {
+    inferred: Inferred,
+    annotated: bool,
+}
+
+struct Checker {
+    scopes: Vec<HashMap<String, Binding>>,
+    records: HashMap<String, Vec<String>>,
+    returns: Vec<(String, Type)>,
+    errors: Vec<SlashError>,
+    include_dir: PathBuf,
+    included: HashSet<PathBuf>,
+}
+
+// Checks the script and returns all the errors found
+pub fn check(source: &str, include_dir: PathBuf) -> Vec<SlashError> {
+    let mut checker = Checker {
+        scopes: vec!(builtins()),
+        records: HashMap::new(),
+        returns: Vec::new(),
+        errors: Vec::new(),
+        include_dir,
+        included: HashSet::new(),
+    };
+    match SlashParser::parse(Rule::file, source) {
+        Ok(mut pairs) => {
+            let file = pairs.next().unwrap();
+            checker.collect_records(&file);
+            checker.statements(file);
+        }
+        Err(e) => checker.errors.push(SlashError::from(e))
+    }
+    checker.errors
+}
+
+// The types of the builtin functions. Builtins that take a function or any kind of collection take Any
+fn builtins() -> HashMap<String, Binding> {
+    use Type::*;
+    let builtin = |params: &[Type], optional: usize, returns: Type| {
+        let required = params.len() - optional;
+        let params = params.iter().enumerate()
+            .map(|(i, ty)| Param { name: (i + 1).to_string(), ty: ty.clone(), optional: i >= required, rest: false })
+            .collect();
+        FunctionType { params, returns, named: false }
+    };
+    let variadic = |returns: Type| FunctionType {
+        params: vec!(Param { name: "1".to_owned(), ty: Any, optional: true, rest: true }),
+        returns,
+        named: false,
+    };
+    let functions = vec!(
+        ("print", variadic(Any)), ("println", variadic(Any)), ("eprint", variadic(Any)), ("eprintln", variadic(Any)),
+        ("len", builtin(&[Any], 0, Number)), ("to_str", builtin(&[Any], 0, String)), ("parse_number", builtin(&[String], 0, Number)),
+        ("is_number", builtin(&[Any], 0, Number)), ("is_list", builtin(&[Any], 0, Number)), ("is_table", builtin(&[Any], 0, Number)),
+        ("is_string", builtin(&[Any], 0, Number)), ("is_process_result", builtin(&[Any], 0, Number)), ("is_function", builtin(&[Any], 0, Number)),
+        ("stdout", builtin(&[ProcessResult], 0, String)), ("stderr", builtin(&[ProcessResult], 0, String)),
+        ("exit_code", builtin(&[ProcessResult], 0, Number)), ("exit", builtin(&[Number], 1, Any)),
+        ("include", builtin(&[String], 0, Any)), ("cwd", builtin(&[], 0, String)),
+        ("split", builtin(&[String, String], 0, List)), ("starts_with", builtin(&[String, String], 0, Number)),
+        ("trim", builtin(&[String, String], 1, String)), ("join", builtin(&[List, String], 1, String)),
+        ("map", builtin(&[Any, Function], 0, List)), ("filter", builtin(&[Any, Function], 0, List)),
+        ("flat_map", builtin(&[Any, Function], 0, List)), ("reduce", builtin(&[Any, Function, Any], 1, Any)),
+        ("any", builtin(&[Any, Function], 0, Number)), ("all", builtin(&[Any, Function], 0, Number)),
+        ("find", builtin(&[Any, Function, Any], 1, Any)), ("sort_by", builtin(&[Any, Function], 0, List)),
+        ("group_by", builtin(&[Any, Function], 0, Table)), ("take", builtin(&[Any, Number], 0, Iterator)),
+        ("skip", builtin(&[Any, Number], 0, Iterator)), ("zip", builtin(&[Any, Any], 0, Iterator)),
+        ("enumerate", builtin(&[Any], 0, Iterator)), ("chain", builtin(&[Any, Any], 0, Iterator)),
+        ("collect", builtin(&[Any], 0, List)), ("entries", builtin(&[Table], 0, List)),
+        ("copy", builtin(&[Any], 0, Any)), ("deep_copy", builtin(&[Any], 0, Any)), ("same", builtin(&[Any, Any], 0, Number)),
+        ("freeze", builtin(&[Any], 0, Any)), ("path_of_script", builtin(&[], 0, String)), ("args", builtin(&[], 0, List)),
+        ("lookup_env_var", builtin(&[String], 0, Any)), ("json_stringify", builtin(&[Any], 0, String)),
+        ("json_parse", builtin(&[String, Function], 1, Any)), ("get", builtin(&[Any, Any, Any], 1, Any)),
+        ("chars", builtin(&[String], 0, List)), ("graphemes", builtin(&[String], 0, List)),
+        ("substring", builtin(&[String, Number, Number], 1, String)), ("upper", builtin(&[String], 0, String)),
+        ("lower", builtin(&[String], 0, String)), ("pad_left", builtin(&[String, Number, String], 1, String)),
+        ("pad_right", builtin(&[String, Number, String], 1, String)), ("reverse", builtin(&[Any], 0, Any)),
+        ("display_width", builtin(&[String], 0, Number)),
+        ("ends_with", builtin(&[String, String], 0, Number)), ("contains", builtin(&[Any, Any], 0, Number)),
+        ("index_of", builtin(&[Any, Any], 0, Number)), ("replace", builtin(&[String, String, String], 0, String)),
+        ("replace_all", builtin(&[String, String, String], 0, String)), ("repeat", builtin(&[String, Number], 0, String)),
+        ("lines", builtin(&[String], 0, List)), ("split_whitespace", builtin(&[String], 0, List)),
+        ("trim_start", builtin(&[String, String], 1, String)), ("trim_end", builtin(&[String, String], 1, String)),
+        ("re_compile", builtin(&[String], 0, Regex)), ("re_match", builtin(&[Any, String], 0, Number)),
+        ("re_find_all", builtin(&[Any, String], 0, List)), ("re_captures", builtin(&[Any, String], 0, Any)),
+        ("re_replace", builtin(&[Any, String, Any], 0, String)), ("re_split", builtin(&[Any, String], 0, List)),
+        ("parse_int", builtin(&[String, Number], 1, Number)), ("floor", builtin(&[Number], 0, Number)),
+        ("ceil", builtin(&[Number], 0, Number)), ("abs", builtin(&[Number], 0, Number)), ("sqrt", builtin(&[Number], 0, Number)),
+        ("sin", builtin(&[Number], 0, Number)), ("cos", builtin(&[Number], 0, Number)), ("tan", builtin(&[Number], 0, Number)),
+        ("asin", builtin(&[Number], 0, Number)), ("acos", builtin(&[Number], 0, Number)), ("atan", builtin(&[Number], 0, Number)),
+        ("atan2", builtin(&[Number, Number], 0, Number)), ("log", builtin(&[Number, Number], 1, Number)),
+        ("round", builtin(&[Number, Number], 1, Number)), ("min", variadic(Any)), ("max", variadic(Any)),
+        ("clamp", builtin(&[Number, Number, Number], 0, Number)), ("random", builtin(&[], 0, Number)),
+        ("random_int", builtin(&[Number, Number], 0, Number)), ("random_seed", builtin(&[Number], 0, Any)),
+        ("format_number", builtin(&[Number, Number, String], 2, String)),
+        ("format", FunctionType {
+            params: vec!(Param { name: "1".to_owned(), ty: String, optional: false, rest: false },
+                         Param { name: "2".to_owned(), ty: Any, optional: true, rest: true }),
+            returns: String,
+            named: false,
+        }),
+    );
+    functions.into_iter()
+        .map(|(name, function)| (name.to_owned(), Binding { inferred: Inferred { ty: Function, function: Some(Rc::new(function)) }, annotated: true }))
+        .collect()
+}
+
+impl Checker {
+    fn error(&mut self, span: &Span, message: &str) {
+        self.errors.push(SlashError::new(span, message));
+    }
+
+    fn declare(&mut self, name: &str, inferred: Inferred, annotated: bool) {
+        self.scopes.last_mut().unwrap().insert(name.to_owned(), Binding { inferred, annotated });
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn in_scope<T>(&mut self, f: impl FnOnce(&mut Checker) -> T) -> T {
+        self.scopes.push(HashMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    // Record types can be used in annotations before their struct declaration, so they are collected first
+    fn collect_records(&mut self, pair: &Pair<Rule>) {
+        for p in pair.clone().into_inner() {
+            if p.as_rule() == Rule::struct_declaration {
+                let names: Vec<_> = p.into_inner().filter(|p| p.as_rule() == Rule::var_name).map(|p| p.as_str().to_owned()).collect();
+                self.records.insert(names[0].clone(), names[1..].to_vec());
+            } else {
+                self.collect_records(&p);
+            }
+        }
+    }
+
+    fn resolve_type(&mut self, name: &str, span: &Span) -> Type {
+        match name {
+            "Any" => Type::Any,
+            "Number" => Type::Number,
+            "String" => Type::String,
+            "List" => Type::List,
+            "Table" => Type::Table,
+            "Function" => Type::Function,
+            "ProcessResult" => Type::ProcessResult,
+            "Iterator" => Type::Iterator,
+            "Regex" => Type::Regex,
+            "Null" => Type::Null,
+            name if self.records.contains_key(name) => Type::Record(name.to_owned()),
+            name => {
+                self.error(span, &format!("Unknown type {}", name));
+                Type::Any
+            }
+        }
+    }
+
+    fn function_type(&mut self, signature: &Signature, generator: bool, span: &Span) -> FunctionType {
+        let params = signature.args.iter().map(|arg| {
+            let ty = match &arg.annotation {
+                Some(annotation) => self.resolve_type(annotation, span),
+                None => Type::Any
+            };
+            Param { name: arg.name.clone(), ty, optional: arg.default.is_some() || arg.rest, rest: arg.rest }
+        }).collect();
+        let returns = match &signature.returns {
+            Some(returns) => self.resolve_type(returns, span),
+            None if generator => Type::Iterator,
+            None => Type::Any
+        };
+        FunctionType { params, returns, named: true }
+    }
+
+    // Checks the body of a function with its parameters declared, a block body or an expression body
+    fn function_body(&mut self, name: &str, function: &FunctionType, body: Pair<Rule>) {
+        let generator = contains_yield(&body);
+        self.in_scope(|checker| {
+            for param in &function.params {
+                let ty = if param.rest { Type::List } else { param.ty.clone() };
+                checker.declare(&param.name, Inferred::of(ty), param.ty.is_known() && !param.rest);
+            }
+            let returns = if generator { Type::Any } else { function.returns.clone() };
+            checker.returns.push((name.to_owned(), returns));
+            if body.as_rule() == Rule::block {
+                checker.statements(body);
+            } else {
+                let span = body.as_span();
+                let inferred = checker.expression(body);
+                checker.check_return(&inferred.ty, &span);
+            }
+            checker.returns.pop();
+        });
+    }
+
+    fn check_return(&mut self, ty: &Type, span: &Span) {
+        if let Some((name, returns)) = self.returns.last() {
+            if !returns.accepts(ty) {
+                let message = format!("Expected the return value of function {} to be a {}, but it is a {}", name, returns.name(), ty.name());
+                self.error(span, &message);
+            }
+        }
+    }
+
+    fn statements(&mut self, pair: Pair<Rule>) {
+        for p in pair.into_inner() {
+            self.statement(p);
+        }
+    }
+
+    fn statement(&mut self, pair: Pair<Rule>) {
+        let span = pair.as_span();
+        match pair.as_rule() {
+            Rule::block => self.in_scope(|checker| checker.statements(pair)),
+            Rule::var_declaration => {
+                let mut pairs = pair.into_inner().peekable();
+                pairs.next_if(|p| p.as_rule() == Rule::constant);
+                let pattern = pairs.next().unwrap();
+                let annotation = pairs.next_if(|p| p.as_rule() == Rule::type_annotation);
+                let mut inferred = self.expression(pairs.next().unwrap());
+                let annotated = annotation.is_some();
+                if let Some(annotation) = annotation {
+                    let annotation_span = annotation.as_span();
+                    let ty = self.resolve_type(&annotated_type(annotation), &annotation_span);
+                    if !ty.accepts(&inferred.ty) {
+                        self.error(&annotation_span, &format!("Expected {} to be a {}, but it is a {}", pattern.as_str(), ty.name(), inferred.ty.name()));
+                    }
+                    if ty.is_known() { inferred.ty = ty; }
+                }
+                if pattern.as_rule() == Rule::var_name {
+                    self.declare(pattern.as_str(), inferred, annotated);
+                } else {
+                    self.bind_pattern(pattern);
+                }
+            }
+            Rule::function_declaration => {
+                let mut pairs = pair.into_inner();
+                let name = pairs.next().unwrap();
+                let mut children: Vec<_> = pairs.collect();
+                let body = children.pop().unwrap();
+                match signature(children.into_iter()) {
+                    Ok(signature) => {
+                        let function = Rc::new(self.function_type(&signature, contains_yield(&body), &name.as_span()));
+                        self.declare(name.as_str(), Inferred { ty: Type::Function, function: Some(function.clone()) }, false);
+                        self.function_body(name.as_str(), &function, body);
+                    }
+                    Err(e) => self.errors.push(e)
+                }
+            }
+            Rule::struct_declaration => {
+                let mut pairs = pair.into_inner();
+                let name = pairs.next().unwrap().as_str();
+                let params = pairs.map(|p| Param { name: p.as_str().to_owned(), ty: Type::Any, optional: false, rest: false }).collect();
+                let function = FunctionType { params, returns: Type::Record(name.to_owned()), named: true };
+                self.declare(name, Inferred { ty: Type::Function, function: Some(Rc::new(function)) }, false);
+            }
+            Rule::var_assignment => {
+                let mut pairs = pair.into_inner();
+                let name = pairs.next().unwrap();
+                let operator = pairs.next().unwrap();
+                let expression = pairs.next().unwrap();
+                let expression_span = expression.as_span();
+                let inferred = self.expression(expression);
+                let binding = match self.lookup(name.as_str()) {
+                    Some(binding) => binding.clone(),
+                    None => return
+                };
+                let ty = match operator.as_rule() {
+                    Rule::assign => inferred.ty,
+                    _ => self.binary(&operator, &binding.inferred.ty, &inferred.ty)
+                };
+                if binding.annotated {
+                    if !binding.inferred.ty.accepts(&ty) {
+                        self.error(&expression_span, &format!("Expected {} to be a {}, but it is a {}", name.as_str(), binding.inferred.ty.name(), ty.name()));
+                    }
+                } else if binding.inferred.ty != ty || binding.inferred.function.is_some() {
+                    // The type of a variable without annotation is only known while it is not assigned another type
+                    self.assign(name.as_str(), Inferred::of(Type::Any));
+                }
+            }
+            Rule::path_var_assignment => {
+                let mut pairs = pair.into_inner().peekable();
+                pairs.next_if(|p| p.as_rule() == Rule::auto_create);
+                let name = pairs.next().unwrap();
+                let record = match self.lookup(name.as_str()).map(|b| b.inferred.ty.clone()) {
+                    Some(Type::Record(record)) => Some(record),
+                    _ => None
+                };
+                let mut first = true;
+                for p in pairs {
+                    match p.as_rule() {
+                        Rule::field_accessor if first => {
+                            if let Some(record) = &record {
+                                self.check_field(record, p.into_inner().next().unwrap());
+                            }
+                        }
+                        Rule::field_accessor | Rule::push_accessor => {}
+                        Rule::index_accessor => { self.term(p.into_inner().next().unwrap()); }
+                        Rule::expression => { self.expression(p); }
+                        _ => {}
+                    }
+                    first = false;
+                }
+            }
+            Rule::export_statement => {
+                let mut pairs = pair.into_inner();
+                let name = pairs.next().unwrap().as_str();
+                if let Some(expression) = pairs.next() {
+                    let inferred = self.expression(expression);
+                    self.declare(name, inferred, false);
+                }
+            }
+            Rule::while_statement | Rule::do_while_statement | Rule::if_statement => {
+                for p in pair.into_inner() {
+                    if p.as_rule() == Rule::expression { self.expression(p); } else { self.statement(p); }
+                }
+            }
+            Rule::labeled_loop => self.statement(pair.into_inner().nth(1).unwrap()),
+            Rule::for_in_statement => {
+                let mut pairs: Vec<_> = pair.into_inner().collect();
+                let block = pairs.pop().unwrap();
+                let expression = pairs.pop().unwrap();
+                self.expression(expression);
+                self.in_scope(|checker| {
+                    pairs.into_iter().for_each(|p| checker.bind_pattern(p));
+                    checker.statement(block);
+                });
+            }
+            Rule::for_std_statement => {
+                let mut pairs = pair.into_inner();
+                let name = pairs.next().unwrap().as_str().to_owned();
+                let init = pairs.next().unwrap();
+                self.in_scope(|checker| {
+                    let inferred = checker.expression(init);
+                    checker.declare(&name, inferred, false);
+                    pairs.for_each(|p| if p.as_rule() == Rule::expression { checker.expression(p); } else { checker.statement(p); });
+                });
+            }
+            Rule::return_statement => {
+                let expression = pair.into_inner().next().unwrap();
+                let expression_span = expression.as_span();
+                let inferred = self.expression(expression);
+                self.check_return(&inferred.ty, &expression_span);
+            }
+            Rule::yield_statement => { self.expression(pair.into_inner().next().unwrap()); }
+            Rule::match_statement => {
+                let mut pairs = pair.into_inner();
+                self.expression(pairs.next().unwrap());
+                for arm in pairs {
+                    self.in_scope(|checker| checker.match_arm(arm));
+                }
+            }
+            Rule::function_call_statement => {
+                let mut pairs = pair.into_inner();
+                let name = pairs.next().unwrap();
+                let args = pairs.next().unwrap();
+                if name.as_str() == "include" && matches!(self.lookup("include"), Some(b) if b.annotated) {
+                    self.include(args.clone());
+                }
+                let callee = self.lookup(name.as_str()).map(|b| b.inferred.clone());
+                let span = name.as_span();
+                self.call(name.as_str(), callee, climb(args), None, &span);
+            }
+            Rule::chain => self.chain(pair),
+            Rule::break_statement | Rule::continue_statement | Rule::EOI => {}
+            _ => self.error(&span, &format!("Unexpected statement {:?}", pair.as_rule()))
+        }
+    }
+
+    // Commands are not run, only the expressions in them are checked. A capture declares a process result
+    fn chain(&mut self, pair: Pair<Rule>) {
+        for p in pair.into_inner() {
+            match p.as_rule() {
+                Rule::expression => { self.expression(p); }
+                Rule::capture => {
+                    let name = p.into_inner().next().unwrap().as_str();
+                    self.declare(name, Inferred::of(Type::ProcessResult), false);
+                }
+                _ => self.chain(p)
+            }
+        }
+    }
+
+    // Checks an included file in the current scope, when the file name is a plain string literal
+    fn include(&mut self, args: Pair<Rule>) {
+        let literal = match args.clone().into_inner().next().and_then(|t| t.into_inner().next()) {
+            Some(literal) if literal.as_rule() == Rule::string_literal => literal,
+            _ => return
+        };
+        let span = literal.as_span();
+        let mut contents = literal.into_inner();
+        let file = match (contents.next(), contents.next()) {
+            (Some(content), None) if content.as_rule() == Rule::string_content => content.as_str().to_owned(),
+            _ => return
+        };
+        let path = if file.starts_with('/') { PathBuf::from(&file) } else { self.include_dir.join(&file) };
+        if !self.included.insert(path.clone()) { return; }
+
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(_) => return self.error(&span, &format!("Failed to load content of file {}", path.display()))
+        };
+        match SlashParser::parse(Rule::file, &source) {
+            Ok(mut pairs) => {
+                let file = pairs.next().unwrap();
+                self.collect_records(&file);
+                let mut include_dir = path.clone();
+                include_dir.pop();
+                let saved_include_dir = std::mem::replace(&mut self.include_dir, include_dir);
+                self.statements(file);
+                self.include_dir = saved_include_dir;
+            }
+            Err(e) => self.errors.push(SlashError::from(e))
+        }
+    }
+
+    fn assign(&mut self, name: &str, inferred: Inferred) {
+        if let Some(binding) = self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+            binding.inferred = inferred;
+        }
+    }
+
+    fn check_field(&mut self, record: &str, field: Pair<Rule>) {
+        if let Some(fields) = self.records.get(record) {
+            if !fields.iter().any(|f| f == field.as_str()) {
+                let message = format!("{} has no field named {}", record, field.as_str());
+                self.error(&field.as_span(), &message);
+            }
+        }
+    }
+
+    // Declares the names bound by a pattern, the types of the values are only known for type patterns
+    fn bind_pattern(&mut self, pattern: Pair<Rule>) {
+        match pattern.as_rule() {
+            Rule::var_name => self.declare(pattern.as_str(), Inferred::of(Type::Any), false),
+            Rule::rest_pattern => {
+                if let Some(name) = pattern.into_inner().next() {
+                    self.declare(name.as_str(), Inferred::of(Type::List), false);
+                }
+            }
+            Rule::type_pattern => {
+                let mut pairs = pattern.into_inner();
+                let type_pair = pairs.next().unwrap();
+                if let Some(name) = pairs.next() {
+                    let ty = self.resolve_type(type_pair.as_str(), &type_pair.as_span());
+                    self.declare(name.as_str(), Inferred::of(ty), false);
+                }
+            }
+            Rule::table_pattern_field => {
+                let pairs: Vec<_> = pattern.into_inner().collect();
+                match pairs.len() {
+                    1 => self.bind_pattern(pairs[0].clone()),
+                    _ => self.bind_pattern(pairs[1].clone())
+                }
+            }
+            Rule::expression => { self.expression(pattern); }
+            Rule::list_pattern | Rule::table_pattern | Rule::match_pattern => {
+                pattern.into_inner().for_each(|p| self.bind_pattern(p));
+            }
+            _ => {}
+        }
+    }
+
+    // Checks a match arm of a match statement or match expression, and returns the type of its value
+    fn match_arm(&mut self, arm: Pair<Rule>) -> Type {
+        let mut ty = Type::Any;
+        for p in arm.into_inner() {
+            match p.as_rule() {
+                Rule::match_pattern => self.bind_pattern(p),
+                Rule::match_guard => { self.expression(p.into_inner().next().unwrap()); }
+                Rule::block => self.statement(p),
+                _ => ty = self.expression(p).ty
+            }
+        }
+        ty
+    }
+
+    fn expression(&mut self, expression: Pair<Rule>) -> Inferred {
+        self.node(climb(expression))
+    }
+
+    fn node(&mut self, node: Node) -> Inferred {
+        match node {
+            Node::Term(pair) => self.term(pair),
+            Node::Infix(lhs, op, rhs) => {
+                let op_span = op.as_span();
+                match op.as_rule() {
+                    Rule::function_call_indicator if is_method(&lhs) => {
+                        if let Node::Infix(object, _, method) = *lhs {
+                            let object = self.node(*object);
+                            let name = if let Node::Term(name) = *method { name.as_str().to_owned() } else { unreachable!() };
+                            let callee = match object.ty {
+                                Type::Table | Type::Any => None,
+                                _ => self.lookup(&name).map(|b| b.inferred.clone())
+                            };
+                            match callee {
+                                Some(callee) => self.call(&name, Some(callee), *rhs, Some(object.ty), &op_span),
+                                None => self.call(&name, None, *rhs, None, &op_span)
+                            }
+                        } else {
+                            unreachable!()
+                        }
+                    }
+                    Rule::function_call_indicator => {
+                        let name = match lhs.as_ref() {
+                            Node::Term(name) if name.as_rule() == Rule::var_name => name.as_str().to_owned(),
+                            _ => "(expr)".to_owned()
+                        };
+                        let callee = self.node(*lhs);
+                        self.call(&name, Some(callee), *rhs, None, &op_span)
+                    }
+                    Rule::infix_dot => {
+                        let object = self.node(*lhs);
+                        match *rhs {
+                            Node::Term(field) if field.as_rule() == Rule::var_name => {
+                                if let Type::Record(record) = &object.ty {
+                                    self.check_field(record, field);
+                                }
+                            }
+                            rhs => { self.node(rhs); }
+                        }
+                        Inferred::of(Type::Any)
+                    }
+                    Rule::or | Rule::and => {
+                        let lhs = self.node(*lhs).ty;
+                        let rhs = self.node(*rhs).ty;
+                        Inferred::of(Type::unify(vec!(lhs, rhs)))
+                    }
+                    _ => {
+                        let lhs = self.node(*lhs).ty;
+                        let rhs = self.node(*rhs).ty;
+                        Inferred::of(self.binary(&op, &lhs, &rhs))
+                    }
+                }
+            }
+        }
+    }
+
+    // The type of a binary operator or compound assignment applied to operands of known types
+    fn binary(&mut self, op: &Pair<Rule>, lhs: &Type, rhs: &Type) -> Type {
+        let span = op.as_span();
+        let both_known = lhs.is_known() && rhs.is_known();
+        match op.as_rule() {
+            Rule::add | Rule::add_assign => {
+                if both_known && (lhs != rhs || !matches!(lhs, Type::Number | Type::String | Type::List)) {
+                    self.error(&span, &format!("Type mismatch, cannot apply {} to {} and {}", op.as_str(), lhs.name(), rhs.name()));
+                    return Type::Any;
+                }
+                Type::unify(vec!(lhs.clone(), rhs.clone()))
+            }
+            Rule::subtract | Rule::multiply | Rule::divide | Rule::modulo | Rule::power |
+            Rule::subtract_assign | Rule::multiply_assign | Rule::divide_assign | Rule::modulo_assign => {
+                if !Type::Number.accepts(lhs) || !Type::Number.accepts(rhs) {
+                    self.error(&span, &format!("Type mismatch, cannot apply {} to {} and {}", op.as_str(), lhs.name(), rhs.name()));
+                }
+                Type::Number
+            }
+            Rule::equals | Rule::not_equals | Rule::less_than | Rule::greater_than |
+            Rule::less_than_or_equals | Rule::greater_than_or_equals => {
+                if both_known && lhs != rhs && *lhs != Type::Null && *rhs != Type::Null {
+                    self.error(&span, &format!("Type mismatch in comparison. Cannot compare {} to {}", lhs.name(), rhs.name()));
+                }
+                Type::Number
+            }
+            _ => Type::Any
+        }
+    }
+
+    // Checks the arguments of a call against the type of the called function, and returns the type of the result
+    fn call(&mut self, name: &str, callee: Option<Inferred>, args: Node, object: Option<Type>, span: &Span) -> Inferred {
+        let mut arg_nodes = vec!();
+        flatten_arguments(args, &mut arg_nodes);
+        let function = match callee {
+            Some(Inferred { ty, .. }) if ty.is_known() && ty != Type::Function => {
+                self.error(span, "The left hand side does not evaluate to a function");
+                None
+            }
+            Some(callee) => callee.function,
+            None => None
+        };
+
+        let mut positional: Vec<Type> = object.into_iter().collect();
+        let mut named = vec!();
+        for arg in arg_nodes {
+            match arg {
+                Node::Term(pair) if pair.as_rule() == Rule::empty_expression_list => {}
+                Node::Infix(arg_name, op, value) if op.as_rule() == Rule::map_field_constructor && matches!(arg_name.as_ref(), Node::Term(n) if n.as_rule() == Rule::var_name) => {
+                    let ty = self.node(*value).ty;
+                    if let Node::Term(arg_name) = *arg_name {
+                        named.push((arg_name, ty));
+                    }
+                }
+                arg => {
+                    let ty = self.node(arg).ty;
+                    positional.push(ty);
+                }
+            }
+        }
+
+        let function = match function {
+            Some(function) => function,
+            None => return Inferred::of(Type::Any)
+        };
+        let fixed = function.params.iter().take_while(|p| !p.rest).count();
+        if fixed == function.params.len() && positional.len() > fixed {
+            self.error(span, &format!("Parameter mismatch for function call {}, expected {} arguments but got {}", name, fixed, positional.len()));
+        }
+        let mut given = vec!(false; function.params.len());
+        for (i, ty) in positional.iter().enumerate() {
+            let index = if i < fixed { i } else if fixed < function.params.len() { fixed } else { break };
+            given[index] = true;
+            self.check_argument(name, &function.params[index], ty, span);
+        }
+        for (arg_name, ty) in named {
+            match function.params.iter().position(|p| p.name == arg_name.as_str() && !p.rest) {
+                Some(index) if function.named => {
+                    given[index] = true;
+                    self.check_argument(name, &function.params[index], &ty, span);
+                }
+                _ => self.error(&arg_name.as_span(), &format!("Function {} has no parameter named {}", name, arg_name.as_str()))
+            }
+        }
+        for (param, given) in function.params.iter().zip(given) {
+            if !given && !param.optional {
+                self.error(span, &format!("Missing argument {} for function call {}", param.name, name));
+            }
+        }
+        Inferred::of(function.returns.clone())
+    }
+
+    fn check_argument(&mut self, name: &str, param: &Param, ty: &Type, span: &Span) {
+        if !param.ty.accepts(ty) {
+            self.error(span, &format!("Expected argument {} of function {} to be a {}, but it is a {}", param.name, name, param.ty.name(), ty.name()));
+        }
+    }
+
+    fn term(&mut self, pair: Pair<Rule>) -> Inferred {
+        match pair.as_rule() {
+            Rule::literal => self.term(pair.into_inner().next().unwrap()),
+            Rule::numeric_literal => Inferred::of(Type::Number),
+            Rule::null_literal => Inferred::of(Type::Null),
+            Rule::regex_literal => Inferred::of(Type::Regex),
+            Rule::string_literal => {
+                for p in pair.into_inner() {
+                    if p.as_rule() == Rule::string_interpolation {
+                        self.expression(p.into_inner().next().unwrap());
+                    }
+                }
+                Inferred::of(Type::String)
+            }
+            Rule::raw_string_literal | Rule::heredoc_literal | Rule::env_var => Inferred::of(Type::String),
+            Rule::list_literal => {
+                self.expression(pair.into_inner().next().unwrap());
+                Inferred::of(Type::List)
+            }
+            Rule::map_literal => {
+                self.expression(pair.into_inner().next().unwrap());
+                Inferred::of(Type::Table)
+            }
+            Rule::var_name => self.lookup(pair.as_str()).map(|b| b.inferred.clone()).unwrap_or_else(|| Inferred::of(Type::Any)),
+            Rule::expression | Rule::value_expression | Rule::postfix_expression => self.expression(pair),
+            Rule::slice_range => {
+                for bound in pair.into_inner() {
+                    self.expression(bound.into_inner().next().unwrap());
+                }
+                Inferred::of(Type::Any)
+            }
+            Rule::not_expression => {
+                self.expression(pair.into_inner().next().unwrap());
+                Inferred::of(Type::Number)
+            }
+            Rule::negate_expression => {
+                let span = pair.as_span();
+                let ty = self.expression(pair).ty;
+                if !Type::Number.accepts(&ty) {
+                    self.error(&span, &format!("Negation not defined on value {}", ty.name()));
+                }
+                Inferred::of(Type::Number)
+            }
+            Rule::if_expression => {
+                let children: Vec<_> = pair.into_inner().collect();
+                let last = children.len() - 1;
+                let mut types = vec!();
+                for (i, p) in children.into_iter().enumerate() {
+                    let ty = self.expression(p).ty;
+                    if i % 2 == 1 || i == last { types.push(ty); }
+                }
+                Inferred::of(Type::unify(types))
+            }
+            Rule::match_expression => {
+                let mut pairs = pair.into_inner();
+                self.expression(pairs.next().unwrap());
+                let types = pairs.map(|arm| self.in_scope(|checker| checker.match_arm(arm))).collect();
+                Inferred::of(Type::unify(types))
+            }
+            Rule::anonymous_function => {
+                let span = pair.as_span();
+                let mut children: Vec<_> = pair.into_inner().collect();
+                let body = children.pop().unwrap();
+                match signature(children.into_iter()) {
+                    Ok(signature) => {
+                        let function = self.function_type(&signature, contains_yield(&body), &span);
+                        self.function_body("(lambda)", &function, body);
+                        Inferred { ty: Type::Function, function: Some(Rc::new(function)) }
+                    }
+                    Err(e) => {
+                        self.errors.push(e);
+                        Inferred::of(Type::Function)
+                    }
+                }
+            }
+            _ => Inferred::of(Type::Any)
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::value::Value;
 use std::rc::Rc;
 use std::cell::RefCell;
@@ -6,6 +6,8 @@ use std::cell::RefCell;
 #[derive(Debug)]
 pub struct ClosureData {
     variables: HashMap<String, Value>,
+    constants: HashSet<String>,
+    builtins: HashSet<String>,
     exports: Vec<String>,
     parent: Option<Rc<RefCell<ClosureData>>>
 }
@@ -19,6 +21,8 @@ impl Closure {
             ClosureData {
                 parent,
                 variables: HashMap::new(),
+                constants: HashSet::new(),
+                builtins: HashSet::new(),
                 exports: Vec::new()
             })))
     }
@@ -41,15 +45,47 @@ impl Closure {
         }
     }
 
-    pub fn declare(&self, var_name: &str, value: Value) {
+    // Declares a variable in this scope, replacing a variable with the same name unless it is a constant
+    pub fn declare(&self, var_name: &str, value: Value) -> Result<(), String> {
+        self.check_declaration(var_name)?;
         self.0.borrow_mut().variables.insert(String::from(var_name), value);
+        Ok(())
     }
 
-    pub fn assign(&mut self, var_name: &str, value: Value) {
+    pub fn declare_constant(&self, var_name: &str, value: Value) -> Result<(), String> {
+        self.declare(var_name, value)?;
+        self.0.borrow_mut().constants.insert(String::from(var_name));
+        Ok(())
+    }
+
+    pub fn declare_builtin(&self, var_name: &str, value: Value) {
+        let mut data = self.0.borrow_mut();
+        data.variables.insert(String::from(var_name), value);
+        data.constants.insert(String::from(var_name));
+        data.builtins.insert(String::from(var_name));
+    }
+
+    // Checks that a variable may be declared in this scope. A constant cannot be declared again in the same scope
+    pub fn check_declaration(&self, var_name: &str) -> Result<(), String> {
+        if self.0.borrow().constants.contains(var_name) {
+            return Err(format!("Constant {} is already declared in this scope", var_name));
+        }
+        Ok(())
+    }
+
+    pub fn assign(&mut self, var_name: &str, value: Value) -> Result<(), String> {
         if let Some(closure) = self.find_closure(var_name) {
-            closure.borrow_mut().variables.insert(String::from(var_name), value);
+            let mut data = closure.borrow_mut();
+            if data.builtins.contains(var_name) {
+                return Err(format!("Cannot assign to the builtin function {}", var_name));
+            }
+            if data.constants.contains(var_name) {
+                return Err(format!("Cannot assign to constant {}", var_name));
+            }
+            data.variables.insert(String::from(var_name), value);
+            Ok(())
         } else {
-            panic!("Variable {} not defined", var_name)
+            Err(format!("Variable {} not defined.", var_name))
         }
     }
 
@@ -1,13 +1,12 @@
 use pest::iterators::Pair;
 use crate::closure::Closure;
-use crate::{Rule, value::Value, Slash};
+use crate::{Rule, value::{Value, Container}, Slash};
 use lazy_static::lazy_static;
 use pest::prec_climber::{Assoc, PrecClimber, Operator};
-use crate::function::{FunctionCallResult, Function};
-use std::collections::HashMap;
+use crate::function::{FunctionCallResult, Function, FunctionBody, NamedArg, signature};
+use indexmap::IndexMap;
 use std::rc::Rc;
 use crate::error::SlashError;
-use std::cell::RefCell;
 use std::env;
 use pest::Span;
 
@@ -29,15 +28,17 @@ lazy_static! {
         PrecClimber::new(vec![
             Operator::new(arg_list_constructor, Left) | Operator::new(slice_constructor, Left),
             Operator::new(map_field_constructor, Left),
+            Operator::new(coalesce, Left),
             Operator::new(or, Left),
             Operator::new(and, Left),
             Operator::new(equals, Left) | Operator::new(not_equals, Left),
-            Operator::new(less_than, Left) | Operator::new(greater_than, Left),
+            Operator::new(less_than, Left) | Operator::new(greater_than, Left) |
+                Operator::new(less_than_or_equals, Left) | Operator::new(greater_than_or_equals, Left),
             Operator::new(add, Left) | Operator::new(subtract, Left),
-            Operator::new(multiply, Left) | Operator::new(divide, Left),
+            Operator::new(multiply, Left) | Operator::new(divide, Left) | Operator::new(modulo, Left),
             Operator::new(power, Right),
-            Operator::new(infix_dot, Left),
-            Operator::new(function_call_indicator, Left) | Operator::new(indexer, Left)
+            Operator::new(infix_dot, Left) | Operator::new(function_call_indicator, Left) | Operator::new(indexer, Left) |
+                Operator::new(optional_dot, Left) | Operator::new(optional_indexer, Left)
         ])
     };
 }
@@ -53,208 +54,435 @@ pub fn evaluate_to_value(expression: Pair<Rule>, closure: &mut Closure, slash: &
     }
 }
 
-pub fn evaluate_to_args<'a>(expression: Pair<'a, Rule>, closure: &mut Closure, slash: &Slash<'a>) -> Result<(Vec<Value>, Vec<Span<'a>>), SlashError> {
-    use EvalResult::*;
+pub enum Iterable {
+    Value(Value),
+    Range(f64, f64),
+}
+
+// Evaluates the expression of a for-in loop, a range from..to is returned without creating a list
+pub fn evaluate_to_iterable(expression: Pair<Rule>, closure: &mut Closure, slash: &Slash) -> Result<Iterable, SlashError> {
     let expression_span = expression.as_span();
-    let res = do_climb(expression, closure, slash);
-    match res? {
-        Val(v, span) => Ok((vec!(v), vec!(span))),
-        Var(var, span) => Ok((vec!(closure.lookup(&var)), vec!(span))),
-        ArgList(vals) => {
-            let mut values = vec!();
-            let mut spans = vec!();
-            for (val, span) in vals {
-                values.push(val);
-                spans.push(span);
-            }
-            Ok((values, spans))
-        }
-        _ => Err(SlashError::new(&expression_span, &format!("Syntax error, expected an expression that evaluates to arguments")))
+    match do_climb(expression, closure, slash)? {
+        EvalResult::Slice(Value::Number(from), Value::Number(to), _) => Ok(Iterable::Range(from, to)),
+        EvalResult::Slice(from, to, span) => Err(SlashError::new(&span, &format!("Expected a range of numbers, but got {} and {}", from.value_type(), to.value_type()))),
+        EvalResult::Val(v, _) => Ok(Iterable::Value(v)),
+        EvalResult::Var(var, _) => Ok(Iterable::Value(closure.lookup(&var))),
+        _ => Err(SlashError::new(&expression_span, "Syntax error, expected an expression that evaluates to a value or a range"))
     }
 }
 
+// Evaluates the bounds of a slice in an index, from..to, from.. or ..to. A missing bound is null
+pub fn evaluate_slice_range(slice_range: Pair<Rule>, closure: &mut Closure, slash: &Slash) -> Result<(Value, Value), SlashError> {
+    let mut from = Value::Null;
+    let mut to = Value::Null;
+    for bound in slice_range.into_inner() {
+        let value = evaluate_to_value(bound.clone().into_inner().next().unwrap(), closure, slash)?;
+        if bound.as_rule() == Rule::slice_start { from = value } else { to = value }
+    }
+    Ok((from, to))
+}
 
-fn do_climb<'a>(expression: Pair<'a, Rule>, closure: &mut Closure, slash: &Slash<'a>) -> Result<EvalResult<'a>, SlashError> {
-    use EvalResult::*;
-    let cl = RefCell::new(closure);
+pub type CallArgs<'a> = (Vec<Value>, Vec<Span<'a>>, Vec<NamedArg<'a>>);
+
+pub fn evaluate_call_args<'a>(expression: Pair<'a, Rule>, closure: &mut Closure, slash: &Slash<'a>) -> Result<CallArgs<'a>, SlashError> {
     let expression_span = expression.as_span();
-    let infix_expression_span = expression.as_span();
+    let mut spans = vec!();
+    let mut named = vec!();
+    let values = call_arguments(climb(expression), &mut spans, &mut named, &expression_span, closure, slash)?;
+    Ok((values, spans, named))
+}
+
+
+pub(crate) enum Node<'a> {
+    Term(Pair<'a, Rule>),
+    Infix(Box<Node<'a>>, Pair<'a, Rule>, Box<Node<'a>>),
+}
+
+pub(crate) fn climb(expression: Pair<Rule>) -> Node {
     PREC_CLIMBER.climb(
         expression.into_inner(),
-        |pair: Pair<Rule>| {
-            let expression_span = expression_span.clone();
-            match pair.as_rule() {
-                Rule::literal => {
-                    let literal = pair.into_inner().next().unwrap();
-                    match literal.as_rule() {
-                        Rule::numeric_literal => Ok(Val(Value::Number(literal.as_str().parse::<f64>().unwrap()), expression_span)),
-                        Rule::string_literal => Ok(Val(Value::string_from_syntax(literal.as_str()), expression_span)),
-                        Rule::list_literal => {
-                            let result = do_climb(literal.into_inner().next().unwrap(), &mut cl.borrow_mut(), slash)?;
-                            Ok(Val(Value::List(Rc::new(RefCell::new(
-                                match result {
-                                    Val(v, _) => vec!(v),
-                                    Var(var_name, _span) => vec!(cl.borrow().lookup(&var_name)),
-                                    ArgList(l) => l.into_iter().map(|(v, _s)| v).collect(),
-                                    _ => return Err(SlashError::new(&expression_span, &format!("Expected value or list of values")))
-                                }))), expression_span))
-                        }
-                        Rule::map_literal => {
-                            let mut res = HashMap::new();
-                            match do_climb(literal.into_inner().next().unwrap(), &mut cl.borrow_mut(), slash)? {
-                                FieldMap(key, val, _span) => { res.insert(key, val); }
-                                FieldList(v) => for (k, v) in v { res.insert(k, v); },
-                                ArgList(v) => if !v.is_empty() {
-                                    return Err(SlashError::new(&expression_span, "Expected a field definition"));
-                                }
-                                _ => return Err(SlashError::new(&expression_span, "Expected a field definition"))
-                            }
-                            Ok(Val(Value::Table(Rc::new(RefCell::new(res))), expression_span))
+        |pair: Pair<Rule>| Node::Term(pair),
+        |lhs: Node, op: Pair<Rule>, rhs: Node| Node::Infix(Box::new(lhs), op, Box::new(rhs)),
+    )
+}
+
+fn do_climb<'a>(expression: Pair<'a, Rule>, closure: &mut Closure, slash: &Slash<'a>) -> Result<EvalResult<'a>, SlashError> {
+    let expression_span = expression.as_span();
+    evaluate_node(climb(expression), &expression_span, closure, slash)
+}
+
+fn evaluate_node<'a>(node: Node<'a>, expression_span: &Span<'a>, closure: &mut Closure, slash: &Slash<'a>) -> Result<EvalResult<'a>, SlashError> {
+    match node {
+        Node::Term(pair) => evaluate_term(pair, expression_span.clone(), closure, slash),
+        Node::Infix(lhs, op, rhs) => {
+            match op.as_rule() {
+                // The right hand side of the logical operators is only evaluated if it decides the result
+                Rule::or | Rule::and => {
+                    let op_span = op.as_span();
+                    let lhs = v(evaluate_node(*lhs, expression_span, closure, slash), &op_span, closure)?;
+                    let decided = if op.as_rule() == Rule::or { lhs.is_true() } else { !lhs.is_true() };
+                    if decided {
+                        Ok(EvalResult::Val(lhs, expression_span.clone()))
+                    } else {
+                        let rhs = v(evaluate_node(*rhs, expression_span, closure, slash), &op_span, closure)?;
+                        Ok(EvalResult::Val(rhs, expression_span.clone()))
+                    }
+                }
+                // The right hand side of ?? is only evaluated if the left hand side is null
+                Rule::coalesce => {
+                    let op_span = op.as_span();
+                    match v(evaluate_node(*lhs, expression_span, closure, slash), &op_span, closure)? {
+                        Value::Null => {
+                            let rhs = v(evaluate_node(*rhs, expression_span, closure, slash), &op_span, closure)?;
+                            Ok(EvalResult::Val(rhs, expression_span.clone()))
                         }
-                        _ => unreachable!("{:?}: |{}|", literal.as_rule(), literal.as_str()),
+                        lhs => Ok(EvalResult::Val(lhs, expression_span.clone()))
                     }
                 }
-                Rule::expression => do_climb(pair, &mut cl.borrow_mut(), slash),
-                Rule::not_expression => {
-                    let expr = evaluate_to_value(pair.into_inner().next().unwrap(), &mut cl.borrow_mut(), slash)?;
-                    Ok(Val(Value::Number(if expr.is_true() { 0.0 } else { 1.0 }), expression_span))
+                Rule::function_call_indicator if is_method(&lhs) => {
+                    if let Node::Infix(object, _, method) = *lhs {
+                        evaluate_method_call(*object, *method, op, *rhs, expression_span, closure, slash)
+                    } else {
+                        unreachable!()
+                    }
                 }
-                Rule::var_name => Ok(Var(pair.as_str().to_owned(), expression_span)),
-                Rule::env_var => Ok(Val(evaluate_env_var(&mut cl.borrow_mut(), pair)?, expression_span)),
-                Rule::empty_expression_list => Ok(ArgList(vec!())),
-                Rule::anonymous_function => {
-                    let children: Vec<_> = pair.into_inner().collect();
-                    Ok(Val(Value::Function(Function::User(
-                        Rc::new(children[0..children.len() - 1].iter().map(|p| p.as_str().to_owned()).collect()),
-                        children[children.len() - 1].as_str().to_owned(),
-                        cl.borrow().clone())
-                    ), expression_span))
+                Rule::function_call_indicator => {
+                    let op_span = op.as_span();
+                    let name = match lhs.as_ref() {
+                        Node::Term(name) if name.as_rule() == Rule::var_name => name.as_str(),
+                        _ => "(expr)"
+                    };
+                    let function = v(evaluate_node(*lhs, expression_span, closure, slash), &op_span, closure)?;
+                    let mut spans = vec!(op_span.clone());
+                    let mut named = vec!();
+                    let args = call_arguments(*rhs, &mut spans, &mut named, expression_span, closure, slash)?;
+                    match function.invoke(name, args, spans, named, closure, slash)? {
+                        FunctionCallResult::Value(v) => Ok(EvalResult::Val(v, expression_span.clone())),
+                        FunctionCallResult::NoValue(_st) => Err(SlashError::new(&op_span, "Expected function to return a value"))
+                    }
                 }
                 _ => {
-                    unreachable!("{:?}\n{}", pair.as_rule(), SlashError::new(&pair.as_span(), "Unreachable"));
+                    let lhs = evaluate_node(*lhs, expression_span, closure, slash);
+                    let rhs = evaluate_node(*rhs, expression_span, closure, slash);
+                    evaluate_infix(lhs, op, rhs, expression_span.clone(), closure)
                 }
             }
+        }
+    }
+}
+
+pub(crate) fn is_method(node: &Node) -> bool {
+    if let Node::Infix(_, op, method) = node {
+        if let Node::Term(name) = method.as_ref() {
+            return op.as_rule() == Rule::infix_dot && name.as_rule() == Rule::var_name;
+        }
+    }
+    false
+}
+
+// Evaluates value.name(args). If value is a table with a field called name, the field is called with args,
+// otherwise the function name is called with value as the first argument followed by args.
+fn evaluate_method_call<'a>(object: Node<'a>, method: Node<'a>, op: Pair<'a, Rule>, args: Node<'a>,
+                            expression_span: &Span<'a>, closure: &mut Closure, slash: &Slash<'a>) -> Result<EvalResult<'a>, SlashError> {
+    let op_span = op.as_span();
+    let method = if let Node::Term(name) = method { name } else { unreachable!() };
+    let method_name = method.as_str();
+    let object = v(evaluate_node(object, expression_span, closure, slash), &op_span, closure)?;
+
+    let mut spans = vec!(op_span.clone());
+    let mut named = vec!();
+    let field = if let Value::Table(t) = &object { t.borrow().get(method_name).cloned() } else { None };
+    let (function, args) = if let Some(field) = field {
+        let args = call_arguments(args, &mut spans, &mut named, expression_span, closure, slash)?;
+        (field, args)
+    } else if closure.has_var(method_name) {
+        spans.push(expression_span.clone());
+        let mut all_args = vec!(object);
+        all_args.append(&mut call_arguments(args, &mut spans, &mut named, expression_span, closure, slash)?);
+        (closure.lookup(method_name), all_args)
+    } else {
+        return Err(SlashError::new(&method.as_span(), &format!("No method {} found for value of type {}", method_name, object.value_type())));
+    };
+
+    match function {
+        Value::Function(f) => match f.invoke_with_named(method_name, args, spans, named, closure, slash)? {
+            FunctionCallResult::Value(v) => Ok(EvalResult::Val(v, expression_span.clone())),
+            FunctionCallResult::NoValue(_st) => Err(SlashError::new(&op_span, "Expected function to return a value"))
         },
-        |lhs: Result<EvalResult, SlashError>, op: Pair<Rule>, rhs: Result<EvalResult, SlashError>| {
-            let op_span = op.as_span();
-            let infix_expression_span = infix_expression_span.clone();
-            match op.as_rule() {
-                Rule::function_call_indicator => {
-                    let mut args = vec!();
-                    let mut spans = vec!();
-                    spans.push(op_span.clone());
-                    match rhs? {
-                        Val(v, span) => {
-                            args.push(v);
-                            spans.push(span)
-                        }
-                        ArgList(a) => {
-                            for (val, span) in a {
-                                args.push(val);
-                                spans.push(span)
-                            }
-                        },
-                        Var(var_name, span) => {
-                            args.push(cl.borrow().lookup(&var_name));
-                            spans.push(span)
-                        }
-                        _ => return Err(SlashError::new(&infix_expression_span, &format!("Expected value or list of values")))
-                    }
-                    let lhs = v(lhs, &op_span, &cl.borrow())?;
-                    match lhs.invoke(args, spans, &mut cl.borrow_mut(), slash)? {
-                        FunctionCallResult::Value(v) => Ok(Val(v, infix_expression_span)),
-                        FunctionCallResult::NoValue(_st) => Err(SlashError::new(&op_span, "Expected function to return a value"))
-                    }
+        _ => Err(SlashError::new(&method.as_span(), &format!("{} is not a function, but a {}", method_name, function.value_type())))
+    }
+}
+
+pub(crate) fn flatten_arguments<'a>(node: Node<'a>, args: &mut Vec<Node<'a>>) {
+    match node {
+        Node::Infix(lhs, op, rhs) if op.as_rule() == Rule::arg_list_constructor => {
+            flatten_arguments(*lhs, args);
+            args.push(*rhs);
+        }
+        Node::Term(pair) if pair.as_rule() == Rule::expression => flatten_arguments(climb(pair), args),
+        node => args.push(node)
+    }
+}
+
+fn is_identifier(node: &Node) -> bool {
+    matches!(node, Node::Term(name) if name.as_rule() == Rule::var_name)
+}
+
+// Evaluates the arguments of a call, arguments of the form name: value are collected as named arguments
+fn call_arguments<'a>(args: Node<'a>, spans: &mut Vec<Span<'a>>, named: &mut Vec<NamedArg<'a>>, expression_span: &Span<'a>,
+                      closure: &mut Closure, slash: &Slash<'a>) -> Result<Vec<Value>, SlashError> {
+    use EvalResult::*;
+    let mut arg_nodes = vec!();
+    flatten_arguments(args, &mut arg_nodes);
+
+    let mut values = vec!();
+    for arg in arg_nodes {
+        let arg = match arg {
+            Node::Infix(name, op, value) if op.as_rule() == Rule::map_field_constructor && is_identifier(&name) => {
+                let value = v(evaluate_node(*value, expression_span, closure, slash), &op.as_span(), closure)?;
+                if let Node::Term(name) = *name {
+                    named.push(NamedArg { name: name.as_str().to_owned(), value, span: name.as_span() });
                 }
-                Rule::arg_list_constructor => {
-                    match rhs? {
-                        Val(rhs_val, rhs_span) => {
-                            match lhs? {
-                                Val(lhs_val, lhs_span) => { Ok(ArgList(vec!((lhs_val, lhs_span), (rhs_val, rhs_span)))) },
-                                Var(var_name, lhs_span) => { Ok(ArgList(vec!((cl.borrow().lookup(&var_name), lhs_span), (rhs_val, rhs_span)))) },
-                                ArgList(mut v) => {
-                                    v.push((rhs_val, rhs_span));
-                                    Ok(ArgList(v))
-                                }
-                                _ => Err(SlashError::new(&op_span, "Expected a value or a list of values on left hand side"))
-                            }
-                        }
-                        FieldMap(key, val, _span) => {
-                            match lhs? {
-                                FieldMap(lhs_key, lhs_val, _span) => Ok(FieldList(vec!((lhs_key, lhs_val), (key, val)))),
-                                FieldList(mut v) => {
-                                    v.push((key, val));
-                                    Ok(FieldList(v))
-                                }
-                                _ => Err(SlashError::new(&op_span, "Expected a field or a list of fields on left hand side"))
-                            }
+                continue;
+            }
+            arg => arg
+        };
+        if let Some(named_arg) = named.last() {
+            return Err(SlashError::new(&named_arg.span, "Named arguments must come after all positional arguments"));
+        }
+        match evaluate_node(arg, expression_span, closure, slash)? {
+            Val(v, span) => {
+                values.push(v);
+                spans.push(span)
+            }
+            ArgList(a) => {
+                for (val, span) in a {
+                    values.push(val);
+                    spans.push(span)
+                }
+            },
+            Var(var_name, span) => {
+                values.push(closure.lookup(&var_name));
+                spans.push(span)
+            }
+            _ => return Err(SlashError::new(expression_span, "Expected value or list of values"))
+        }
+    }
+    Ok(values)
+}
+
+fn evaluate_term<'a>(pair: Pair<'a, Rule>, expression_span: Span<'a>, closure: &mut Closure, slash: &Slash<'a>) -> Result<EvalResult<'a>, SlashError> {
+    use EvalResult::*;
+    match pair.as_rule() {
+        Rule::literal => {
+            let literal = pair.into_inner().next().unwrap();
+            match literal.as_rule() {
+                Rule::null_literal => Ok(Val(Value::Null, expression_span)),
+                Rule::regex_literal => {
+                    let source = literal.clone().into_inner().next().unwrap().as_str();
+                    Ok(Val(Value::Regex(slash.regex_literal(source, &literal.as_span())?), expression_span))
+                }
+                Rule::numeric_literal => Ok(Val(Value::Number(literal.as_str().parse::<f64>().unwrap()), expression_span)),
+                Rule::string_literal => Ok(Val(Value::String(evaluate_string_literal(literal, closure, slash)?), expression_span)),
+                Rule::raw_string_literal => Ok(Val(Value::String(evaluate_raw_string_literal(literal)), expression_span)),
+                Rule::heredoc_literal => Ok(Val(Value::String(evaluate_heredoc_literal(literal)), expression_span)),
+                Rule::list_literal => {
+                    let result = do_climb(literal.into_inner().next().unwrap(), closure, slash)?;
+                    Ok(Val(Value::List(Rc::new(Container::new(
+                        match result {
+                            Val(v, _) => vec!(v),
+                            Var(var_name, _span) => vec!(closure.lookup(&var_name)),
+                            ArgList(l) => l.into_iter().map(|(v, _s)| v).collect(),
+                            _ => return Err(SlashError::new(&expression_span, &format!("Expected value or list of values")))
+                        }))), expression_span))
+                }
+                Rule::map_literal => {
+                    let mut res = IndexMap::new();
+                    match do_climb(literal.into_inner().next().unwrap(), closure, slash)? {
+                        FieldMap(key, val, _span) => { res.insert(key, val); }
+                        FieldList(v) => for (k, v) in v { res.insert(k, v); },
+                        ArgList(v) => if !v.is_empty() {
+                            return Err(SlashError::new(&expression_span, "Expected a field definition"));
                         }
-                        _ => Err(SlashError::new(&op_span, "Expected a value or a field on right hand side"))
+                        _ => return Err(SlashError::new(&expression_span, "Expected a field definition"))
                     }
+                    Ok(Val(Value::Table(Rc::new(Container::new(res))), expression_span))
                 }
-                Rule::map_field_constructor => {
-                    let lhs = v(lhs, &op_span, &cl.borrow())?;
-                    let rhs = v(rhs, &op_span, &cl.borrow())?;
-                    Ok(FieldMap(lhs.to_string().to_owned(), rhs, infix_expression_span))
+                _ => unreachable!("{:?}: |{}|", literal.as_rule(), literal.as_str()),
+            }
+        }
+        Rule::expression | Rule::postfix_expression => do_climb(pair, closure, slash),
+        Rule::slice_range => {
+            let (from, to) = evaluate_slice_range(pair, closure, slash)?;
+            Ok(Slice(from, to, expression_span))
+        }
+        Rule::not_expression => {
+            let expr = evaluate_to_value(pair.into_inner().next().unwrap(), closure, slash)?;
+            Ok(Val(Value::Number(if expr.is_true() { 0.0 } else { 1.0 }), expression_span))
+        }
+        Rule::negate_expression => {
+            let expr = evaluate_to_value(pair, closure, slash)?;
+            Ok(Val(expr.negate(&expression_span)?, expression_span))
+        }
+        Rule::if_expression => {
+            let mut branches: Vec<_> = pair.into_inner().collect();
+            let otherwise = branches.pop().unwrap();
+            for branch in branches.chunks(2) {
+                if evaluate_to_value(branch[0].clone(), closure, slash)?.is_true() {
+                    return Ok(Val(evaluate_to_value(branch[1].clone(), closure, slash)?, expression_span));
                 }
-                Rule::slice_constructor => {
-                    let lhs = v(lhs, &op_span, &cl.borrow())?;
-                    let rhs = v(rhs, &op_span, &cl.borrow())?;
-                    Ok(Slice(lhs, rhs, infix_expression_span))
+            }
+            Ok(Val(evaluate_to_value(otherwise, closure, slash)?, expression_span))
+        }
+        Rule::match_expression => {
+            let mut pairs = pair.into_inner();
+            let match_value = evaluate_to_value(pairs.next().unwrap(), closure, slash)?;
+            for match_arm in pairs {
+                let mut patterns: Vec<_> = match_arm.into_inner().collect();
+                let arm_value = patterns.pop().unwrap();
+                let guard = if matches!(patterns.last(), Some(p) if p.as_rule() == Rule::match_guard) { patterns.pop() } else { None };
+                if let Some(mut arm_closure) = slash.matches(&match_value, patterns, guard, closure)? {
+                    return Ok(Val(evaluate_to_value(arm_value, &mut arm_closure, slash)?, expression_span));
                 }
-                Rule::infix_dot => {
-                    let lhs = v(lhs, &op_span, &cl.borrow())?;
-                    let rhs = rhs?;
-                    if let EvalResult::Var(var_name, var_span) = rhs {
-                        if let Value::Table(val) = lhs {
-                            if let Some(field) = val.borrow().get(&var_name) {
-                                return Ok(Val(field.clone(), infix_expression_span));
-                            }
-                        }
-
-                        if cl.borrow().has_var(&var_name) {
-                            // TODO: Partial resolved functions, ie len(str) === str.len()
+            }
+            Err(SlashError::new(&expression_span, &format!("No match arm matched the value {}", match_value.to_json())))
+        }
+        Rule::var_name => Ok(Var(pair.as_str().to_owned(), expression_span)),
+        Rule::env_var => Ok(Val(evaluate_env_var(closure, pair)?, expression_span)),
+        Rule::empty_expression_list => Ok(ArgList(vec!())),
+        Rule::anonymous_function => {
+            let mut children: Vec<_> = pair.into_inner().collect();
+            let body = children.pop().unwrap();
+            let body = match body.as_rule() {
+                Rule::block => FunctionBody::from_block(&body),
+                _ => FunctionBody::Expression(body.as_str().to_owned())
+            };
+            Ok(Val(Value::Function(Function::User(
+                Rc::new(signature(children.into_iter())?),
+                body,
+                closure.clone())
+            ), expression_span))
+        }
+        _ => {
+            unreachable!("{:?}\n{}", pair.as_rule(), SlashError::new(&pair.as_span(), "Unreachable"));
+        }
+    }
+}
 
+fn evaluate_infix<'a>(lhs: Result<EvalResult<'a>, SlashError>, op: Pair<'a, Rule>, rhs: Result<EvalResult<'a>, SlashError>,
+                      infix_expression_span: Span<'a>, closure: &mut Closure) -> Result<EvalResult<'a>, SlashError> {
+    use EvalResult::*;
+    let op_span = op.as_span();
+    match op.as_rule() {
+        Rule::arg_list_constructor => {
+            let rhs = match rhs? {
+                Var(var_name, span) => Val(closure.lookup(&var_name), span),
+                rhs => rhs
+            };
+            match rhs {
+                Val(rhs_val, rhs_span) => {
+                    match lhs? {
+                        Val(lhs_val, lhs_span) => { Ok(ArgList(vec!((lhs_val, lhs_span), (rhs_val, rhs_span)))) },
+                        Var(var_name, lhs_span) => { Ok(ArgList(vec!((closure.lookup(&var_name), lhs_span), (rhs_val, rhs_span)))) },
+                        ArgList(mut v) => {
+                            v.push((rhs_val, rhs_span));
+                            Ok(ArgList(v))
                         }
-
-                        Err(SlashError::new(&var_span, &format!("Identifier {} could not be resolved", &var_name)))
-                    } else {
-                        Err(SlashError::new(&op_span, "Right hand side of a . operator must be an identifier"))
+                        _ => Err(SlashError::new(&op_span, "Expected a value or a list of values on left hand side"))
                     }
                 }
-                Rule::indexer => {
-                    let lhs = v(lhs, &op_span, &cl.borrow());
-                    match rhs? {
-                        Val(v, _) => Ok(Val(lhs?.lookup_by_index(&v, &op_span)?, infix_expression_span)),
-                        Var(var_name, _) => Ok(Val(lhs?.lookup_by_index(&cl.borrow().lookup(&var_name), &op_span)?,infix_expression_span)),
-                        Slice(from, to, _) => {
-                            Ok(Val(lhs?.slice(&from, &to, &op_span)?, infix_expression_span))
+                FieldMap(key, val, _span) => {
+                    match lhs? {
+                        FieldMap(lhs_key, lhs_val, _span) => Ok(FieldList(vec!((lhs_key, lhs_val), (key, val)))),
+                        FieldList(mut v) => {
+                            v.push((key, val));
+                            Ok(FieldList(v))
                         }
-                        _ => Err(SlashError::new(&infix_expression_span, "Expected slice operator, variable or value"))
+                        _ => Err(SlashError::new(&op_span, "Expected a field or a list of fields on left hand side"))
                     }
                 }
-                _ => {
-                    let lhs = v(lhs, &op_span, &cl.borrow());
-                    let rhs = v(rhs, &op_span, &cl.borrow());
-                    match op.as_rule() {
-                        Rule::add => Ok(Val(lhs?.add(&rhs?, &op_span)?, infix_expression_span)),
-                        Rule::subtract => Ok(Val(lhs?.sub(&rhs?, &op_span)?, infix_expression_span)),
-                        Rule::multiply => Ok(Val(lhs?.mul(&rhs?, &op_span)?, infix_expression_span)),
-                        Rule::divide => Ok(Val(lhs?.div(&rhs?, &op_span)?, infix_expression_span)),
-                        Rule::power => Ok(Val(lhs?.powf(&rhs?, &op_span)?, infix_expression_span)),
-                        Rule::or => Ok(Val(lhs?.or(&rhs?), infix_expression_span)),
-                        Rule::and => Ok(Val(lhs?.and(&rhs?), infix_expression_span)),
-                        Rule::equals => Ok(Val(lhs?.equals(&rhs?, &op_span)?, infix_expression_span)),
-                        Rule::not_equals => Ok(Val(lhs?.not_equals(&rhs?, &op_span)?, infix_expression_span)),
-                        Rule::greater_than => Ok(Val(lhs?.greater_than(&rhs?, &op_span)?, infix_expression_span)),
-                        Rule::less_than => Ok(Val(lhs?.less_than(&rhs?, &op_span)?, infix_expression_span)),
-
-                        _ => unreachable!()
+                _ => Err(SlashError::new(&op_span, "Expected a value or a field on right hand side"))
+            }
+        }
+        Rule::map_field_constructor => {
+            let lhs = v(lhs, &op_span, closure)?;
+            let rhs = v(rhs, &op_span, closure)?;
+            Ok(FieldMap(lhs.to_string().to_owned(), rhs, infix_expression_span))
+        }
+        Rule::slice_constructor => {
+            let lhs = v(lhs, &op_span, closure)?;
+            let rhs = v(rhs, &op_span, closure)?;
+            Ok(Slice(lhs, rhs, infix_expression_span))
+        }
+        Rule::infix_dot => {
+            let lhs = v(lhs, &op_span, closure)?;
+            let rhs = rhs?;
+            if let EvalResult::Var(var_name, var_span) = rhs {
+                if let Value::Table(val) = &lhs {
+                    if let Some(field) = val.borrow().get(&var_name) {
+                        return Ok(Val(field.clone(), infix_expression_span));
                     }
                 }
+                if let Value::Record(record_type, fields) = &lhs {
+                    let field = fields.borrow()[record_type.field_index(&var_name, &var_span)?].clone();
+                    return Ok(Val(field, infix_expression_span));
+                }
+
+                Err(SlashError::new(&var_span, &format!("Identifier {} could not be resolved", &var_name)))
+            } else {
+                Err(SlashError::new(&op_span, "Right hand side of a . operator must be an identifier"))
             }
-        },
-    )
+        }
+        Rule::optional_dot => {
+            let lhs = v(lhs, &op_span, closure)?;
+            if let EvalResult::Var(var_name, var_span) = rhs? {
+                Ok(Val(lhs.optional_lookup_by_index(&Value::String(var_name), &var_span)?, infix_expression_span))
+            } else {
+                Err(SlashError::new(&op_span, "Right hand side of a ?. operator must be an identifier"))
+            }
+        }
+        Rule::optional_indexer => {
+            let lhs = v(lhs, &op_span, closure)?;
+            match rhs? {
+                Val(v, _) => Ok(Val(lhs.optional_lookup_by_index(&v, &op_span)?, infix_expression_span)),
+                Var(var_name, _) => Ok(Val(lhs.optional_lookup_by_index(&closure.lookup(&var_name), &op_span)?, infix_expression_span)),
+                Slice(_, _, _) if matches!(lhs, Value::Null) => Ok(Val(Value::Null, infix_expression_span)),
+                Slice(from, to, _) => Ok(Val(lhs.slice(&from, &to, &op_span)?, infix_expression_span)),
+                _ => Err(SlashError::new(&infix_expression_span, "Expected slice operator, variable or value"))
+            }
+        }
+        Rule::indexer => {
+            let lhs = v(lhs, &op_span, closure);
+            match rhs? {
+                Val(v, _) => Ok(Val(lhs?.lookup_by_index(&v, &op_span)?, infix_expression_span)),
+                Var(var_name, _) => Ok(Val(lhs?.lookup_by_index(&closure.lookup(&var_name), &op_span)?,infix_expression_span)),
+                Slice(from, to, _) => {
+                    Ok(Val(lhs?.slice(&from, &to, &op_span)?, infix_expression_span))
+                }
+                _ => Err(SlashError::new(&infix_expression_span, "Expected slice operator, variable or value"))
+            }
+        }
+        _ => {
+            let lhs = v(lhs, &op_span, closure);
+            let rhs = v(rhs, &op_span, closure);
+            match op.as_rule() {
+                Rule::add => Ok(Val(lhs?.add(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::subtract => Ok(Val(lhs?.sub(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::multiply => Ok(Val(lhs?.mul(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::divide => Ok(Val(lhs?.div(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::modulo => Ok(Val(lhs?.rem(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::power => Ok(Val(lhs?.powf(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::equals => Ok(Val(lhs?.equals(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::not_equals => Ok(Val(lhs?.not_equals(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::greater_than => Ok(Val(lhs?.greater_than(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::less_than => Ok(Val(lhs?.less_than(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::greater_than_or_equals => Ok(Val(lhs?.greater_than_or_equals(&rhs?, &op_span)?, infix_expression_span)),
+                Rule::less_than_or_equals => Ok(Val(lhs?.less_than_or_equals(&rhs?, &op_span)?, infix_expression_span)),
+
+                _ => unreachable!()
+            }
+        }
+    }
 }
 
 fn v(r: Result<EvalResult, SlashError>, span: &Span, closure: &Closure) -> Result<Value, SlashError> {
@@ -265,6 +493,44 @@ fn v(r: Result<EvalResult, SlashError>, span: &Span, closure: &Closure) -> Resul
     }
 }
 
+pub fn evaluate_assignment<F>(operator: &Pair<Rule>, current: F, value: Value) -> Result<Value, SlashError>
+    where F: FnOnce() -> Result<Value, SlashError> {
+    let span = operator.as_span();
+    match operator.as_rule() {
+        Rule::assign => Ok(value),
+        Rule::add_assign => current()?.add(&value, &span),
+        Rule::subtract_assign => current()?.sub(&value, &span),
+        Rule::multiply_assign => current()?.mul(&value, &span),
+        Rule::divide_assign => current()?.div(&value, &span),
+        Rule::modulo_assign => current()?.rem(&value, &span),
+        _ => unreachable!()
+    }
+}
+
+pub fn evaluate_string_literal(pair: Pair<Rule>, closure: &mut Closure, slash: &Slash) -> Result<String, SlashError> {
+    let mut res = String::new();
+    for part in pair.into_inner() {
+        match part.as_rule() {
+            Rule::string_content => res.push_str(&Value::unescape_string(part.as_str(), &part.as_span())?),
+            Rule::string_interpolation => {
+                let expression = part.into_inner().next().unwrap();
+                res.push_str(&evaluate_to_value(expression, closure, slash)?.to_string());
+            }
+            _ => unreachable!()
+        }
+    }
+    Ok(res)
+}
+
+pub fn evaluate_raw_string_literal(pair: Pair<Rule>) -> String {
+    pair.into_inner().next().unwrap().as_str().to_owned()
+}
+
+fn evaluate_heredoc_literal(pair: Pair<Rule>) -> String {
+    let content = pair.into_inner().find(|p| p.as_rule() == Rule::heredoc_content).unwrap();
+    Value::dedent(content.as_str())
+}
+
 pub fn evaluate_env_var(closure: &mut Closure, pair: Pair<Rule>) -> Result<Value, SlashError> {
     let var_pair = pair.into_inner().next().unwrap();
     let var_name = var_pair.as_str();
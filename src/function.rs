@@ -1,9 +1,9 @@
 // Code to handle built in and user function calls
 
 use pest::iterators::Pair;
-use crate::value::Value;
+use crate::value::{Value, Container};
 use crate::{Rule, Slash, ExecuteResult};
-use crate::evaluate::{evaluate_to_value, lookup_variable_or_environment, evaluate_to_args};
+use crate::evaluate::{evaluate_to_value, lookup_variable_or_environment, evaluate_call_args};
 use crate::closure::Closure;
 use crate::function::FunctionCallResult::NoValue;
 use crate::pest::Parser;
@@ -13,11 +13,20 @@ use std::str::FromStr;
 use std::{fs, env, fmt};
 use std::ffi::OsStr;
 use std::rc::Rc;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use indexmap::IndexMap;
 use std::fmt::{Debug, Formatter};
+use std::cmp::Ordering;
 use std::path::PathBuf;
 use json::JsonValue;
+use crate::generator::{Generator, contains_yield};
+use crate::iterator::{ValueIterator, to_iterator, next_value};
+use crate::record::RecordType;
+use crate::pattern::check_type;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+use regex::{Regex, Captures};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 
 pub enum FunctionCallResult {
     NoValue(String),
@@ -38,10 +47,98 @@ impl Debug for Builtin {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct FormalArg {
+    pub name: String,
+    pub default: Option<String>,
+    pub rest: bool,
+    pub annotation: Option<String>,
+}
+
+// The formal arguments of a function and the type annotation of its return value
+#[derive(Debug)]
+pub struct Signature {
+    pub args: Vec<FormalArg>,
+    pub returns: Option<String>,
+}
+
+pub struct NamedArg<'a> {
+    pub name: String,
+    pub value: Value,
+    pub span: Span<'a>,
+}
+
+#[derive(Debug, Clone)]
+pub enum FunctionBody {
+    Block(String),
+    Expression(String),
+    Generator(String),
+}
+
+impl FunctionBody {
+    pub fn from_block(block: &Pair<Rule>) -> FunctionBody {
+        if contains_yield(block) {
+            FunctionBody::Generator(block.as_str().to_owned())
+        } else {
+            FunctionBody::Block(block.as_str().to_owned())
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Function {
     Builtin(Builtin),
-    User(Rc<Vec<String>>, String, Closure),
+    User(Rc<Signature>, FunctionBody, Closure),
+    Constructor(Rc<RecordType>),
+}
+
+// Builds the signature of a function declaration from the var_name, default_arg, rest_arg, type_annotation
+// and return_type pairs
+pub fn signature<'a>(pairs: impl Iterator<Item=Pair<'a, Rule>>) -> Result<Signature, SlashError> {
+    let mut formal_args: Vec<FormalArg> = Vec::new();
+    let mut returns = None;
+    for p in pairs {
+        let span = p.as_span();
+        match p.as_rule() {
+            Rule::type_annotation => {
+                formal_args.last_mut().unwrap().annotation = Some(annotated_type(p));
+                continue;
+            }
+            Rule::return_type => {
+                returns = Some(annotated_type(p));
+                continue;
+            }
+            _ => {}
+        }
+        if matches!(formal_args.last(), Some(a) if a.rest) {
+            return Err(SlashError::new(&span, "The rest parameter must be the last parameter"));
+        }
+        let formal_arg = match p.as_rule() {
+            Rule::var_name => FormalArg { name: p.as_str().to_owned(), default: None, rest: false, annotation: None },
+            Rule::rest_arg => {
+                let mut inner = p.into_inner();
+                let name = inner.next().unwrap().as_str().to_owned();
+                FormalArg { name, default: None, rest: true, annotation: inner.next().map(annotated_type) }
+            }
+            Rule::default_arg => {
+                let mut inner = p.into_inner().peekable();
+                let name = inner.next().unwrap().as_str().to_owned();
+                let annotation = inner.next_if(|p| p.as_rule() == Rule::type_annotation).map(annotated_type);
+                FormalArg { name, default: Some(inner.next().unwrap().as_str().to_owned()), rest: false, annotation }
+            }
+            _ => unreachable!("{:?}", p.as_rule())
+        };
+        if formal_args.iter().any(|a| a.name == formal_arg.name) {
+            return Err(SlashError::new(&span, &format!("Duplicate parameter {}", formal_arg.name)));
+        }
+        formal_args.push(formal_arg);
+    }
+    Ok(Signature { args: formal_args, returns })
+}
+
+// The type name of a type_annotation or return_type pair
+pub fn annotated_type(pair: Pair<Rule>) -> String {
+    pair.into_inner().next().unwrap().as_str().to_owned()
 }
 
 pub fn add_builtin_to_closure(closure: &mut Closure) {
@@ -83,7 +180,7 @@ pub fn add_builtin_to_closure(closure: &mut Closure) {
                 match &args[0] {
                     Value::List(l) => Ok(FunctionCallResult::Value(Value::Number(l.borrow().len() as f64))),
                     Value::Table(t) => Ok(FunctionCallResult::Value(Value::Number(t.borrow().len() as f64))),
-                    Value::String(s) => Ok(FunctionCallResult::Value(Value::Number(s.len() as f64))),
+                    Value::String(s) => Ok(FunctionCallResult::Value(Value::Number(s.chars().count() as f64))),
                     _ => Err(invalid_type(&spans[1], &args[0]))
                 }
             }),
@@ -109,6 +206,189 @@ pub fn add_builtin_to_closure(closure: &mut Closure) {
                 }
             }),
         },
+        Builtin {
+            name: "parse_int".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                let radix = verify_optional_formal_args(&args, &spans, 1, 1)?.pop().unwrap();
+                let s = get_string(&args[0], &spans[1])?;
+                let radix = match radix {
+                    Some(radix) => get_number(&radix, &spans[2])?,
+                    None => 10.0
+                };
+                if radix.fract() != 0.0 || !(2.0..=36.0).contains(&radix) {
+                    return Err(SlashError::new(&spans[2], &format!("Expected a radix from 2 to 36, but got {}", radix)));
+                }
+                match i64::from_str_radix(s.trim(), radix as u32) {
+                    Ok(n) => Ok(FunctionCallResult::Value(Value::Number(n as f64))),
+                    Err(_) => Err(SlashError::new(&spans[1], &format!("Parse error for value {} with radix {}", s, radix)))
+                }
+            }),
+        },
+        Builtin {
+            name: "floor".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| math(&args, &spans, f64::floor)),
+        },
+        Builtin {
+            name: "ceil".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| math(&args, &spans, f64::ceil)),
+        },
+        Builtin {
+            name: "abs".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| math(&args, &spans, f64::abs)),
+        },
+        Builtin {
+            name: "sin".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| math(&args, &spans, f64::sin)),
+        },
+        Builtin {
+            name: "cos".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| math(&args, &spans, f64::cos)),
+        },
+        Builtin {
+            name: "tan".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| math(&args, &spans, f64::tan)),
+        },
+        Builtin {
+            name: "asin".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| math(&args, &spans, f64::asin)),
+        },
+        Builtin {
+            name: "acos".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| math(&args, &spans, f64::acos)),
+        },
+        Builtin {
+            name: "atan".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| math(&args, &spans, f64::atan)),
+        },
+        Builtin {
+            name: "atan2".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let y = get_number(&args[0], &spans[1])?;
+                let x = get_number(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(Value::Number(y.atan2(x))))
+            }),
+        },
+        Builtin {
+            name: "sqrt".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                match get_number(&args[0], &spans[1])? {
+                    n if n < 0.0 => Err(SlashError::new(&spans[1], &format!("Cannot take the square root of the negative number {}", n))),
+                    n => Ok(FunctionCallResult::Value(Value::Number(n.sqrt())))
+                }
+            }),
+        },
+        Builtin {
+            name: "log".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                let base = verify_optional_formal_args(&args, &spans, 1, 1)?.pop().unwrap();
+                let n = get_number(&args[0], &spans[1])?;
+                if n <= 0.0 {
+                    return Err(SlashError::new(&spans[1], &format!("Cannot take the logarithm of {}, it must be positive", n)));
+                }
+                match base {
+                    Some(base) => Ok(FunctionCallResult::Value(Value::Number(n.log(get_number(&base, &spans[2])?)))),
+                    None => Ok(FunctionCallResult::Value(Value::Number(n.ln())))
+                }
+            }),
+        },
+        Builtin {
+            name: "round".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                let digits = verify_optional_formal_args(&args, &spans, 1, 1)?.pop().unwrap();
+                let n = get_number(&args[0], &spans[1])?;
+                let digits = match digits {
+                    Some(digits) => get_number(&digits, &spans[2])?.trunc() as i32,
+                    None => 0
+                };
+                let factor = 10f64.powi(digits);
+                let scaled = n * factor;
+                // Digits beyond what a float can represent leave the number as it is
+                if factor == 0.0 || !factor.is_finite() || !scaled.is_finite() {
+                    return Ok(FunctionCallResult::Value(Value::Number(n)));
+                }
+                Ok(FunctionCallResult::Value(Value::Number(scaled.round() / factor)))
+            }),
+        },
+        Builtin {
+            name: "min".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                Ok(FunctionCallResult::Value(extreme(args, &spans, Ordering::Less)?))
+            }),
+        },
+        Builtin {
+            name: "max".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                Ok(FunctionCallResult::Value(extreme(args, &spans, Ordering::Greater)?))
+            }),
+        },
+        Builtin {
+            name: "clamp".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 3)?;
+                let n = get_number(&args[0], &spans[1])?;
+                let low = get_number(&args[1], &spans[2])?;
+                let high = get_number(&args[2], &spans[3])?;
+                if low.is_nan() || high.is_nan() {
+                    return Err(SlashError::new(&spans[0], "Expected the bounds of clamp to be numbers, but got NaN"));
+                }
+                if low > high {
+                    return Err(SlashError::new(&spans[0], &format!("Expected the lower bound {} to not be greater than the upper bound {}", low, high)));
+                }
+                Ok(FunctionCallResult::Value(Value::Number(n.clamp(low, high))))
+            }),
+        },
+        Builtin {
+            name: "random".to_owned(),
+            function: Rc::new(|args, spans, _closure, slash| {
+                verify_formal_args(&args, &spans, 0)?;
+                Ok(FunctionCallResult::Value(Value::Number(slash.rng.borrow_mut().gen::<f64>())))
+            }),
+        },
+        Builtin {
+            name: "random_int".to_owned(),
+            function: Rc::new(|args, spans, _closure, slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let low = get_number(&args[0], &spans[1])?.ceil() as i64;
+                let high = get_number(&args[1], &spans[2])?.floor() as i64;
+                if low > high {
+                    return Err(SlashError::new(&spans[0], &format!("There is no integer from {} to {}", args[0].to_string(), args[1].to_string())));
+                }
+                Ok(FunctionCallResult::Value(Value::Number(slash.rng.borrow_mut().gen_range(low..=high) as f64)))
+            }),
+        },
+        Builtin {
+            name: "random_seed".to_owned(),
+            function: Rc::new(|args, spans, _closure, slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                let seed = get_number(&args[0], &spans[1])?;
+                *slash.rng.borrow_mut() = StdRng::seed_from_u64(seed.to_bits());
+                Ok(NoValue(String::from("random_seed")))
+            }),
+        },
+        Builtin {
+            name: "format_number".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                let mut optional = verify_optional_formal_args(&args, &spans, 1, 2)?;
+                let separator = match optional.pop().unwrap() {
+                    Some(separator) => get_string(&separator, &spans[3])?,
+                    None => ",".to_owned()
+                };
+                let n = get_number(&args[0], &spans[1])?;
+                let formatted = match optional.pop().unwrap() {
+                    Some(decimals) => {
+                        let decimals = get_count(&decimals, &spans[2])?;
+                        if decimals > MAX_PRECISION {
+                            return Err(SlashError::new(&spans[2], &format!("Expected at most {} decimals, but got {}", MAX_PRECISION, decimals)));
+                        }
+                        format!("{:.*}", decimals, n)
+                    }
+                    None => format!("{}", n)
+                };
+                Ok(FunctionCallResult::Value(Value::String(group_thousands(&formatted, &separator))))
+            }),
+        },
         Builtin {
             name: "is_number".to_owned(),
             function: Rc::new(|args, spans, _closure, _slash| {
@@ -191,11 +471,11 @@ pub fn add_builtin_to_closure(closure: &mut Closure) {
         Builtin {
             name: "exit".to_owned(),
             function: Rc::new(|args, spans, _closure, _slash| {
-                verify_formal_args(&args, &spans, 1)?;
-                if let Value::Number(n) = &args[0] {
-                    std::process::exit(*n as i32)
-                } else {
-                    Err(invalid_type_with_expected(&spans[1], &args[0], "Number"))
+                let optional = verify_optional_formal_args(&args, &spans, 0, 1)?;
+                match &optional[0] {
+                    None => std::process::exit(0),
+                    Some(Value::Number(n)) => std::process::exit(*n as i32),
+                    Some(code) => Err(invalid_type_with_expected(&spans[1], code, "Number"))
                 }
             }),
         },
@@ -253,7 +533,7 @@ pub fn add_builtin_to_closure(closure: &mut Closure) {
                     res.push(Value::String(e.to_owned()));
                 }
 
-                Ok(FunctionCallResult::Value(Value::List(Rc::new(RefCell::new(res)))))
+                Ok(FunctionCallResult::Value(Value::List(Rc::new(Container::new(res)))))
             }),
         },
         Builtin {
@@ -266,19 +546,110 @@ pub fn add_builtin_to_closure(closure: &mut Closure) {
             }),
         },
         Builtin {
-            name: "trim".to_owned(),
+            name: "ends_with".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let s = get_string(&args[0], &spans[1])?;
+                let p = get_string(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(Value::Number(if s.ends_with(&p) { 1.0 } else { 0.0 })))
+            }),
+        },
+        Builtin {
+            name: "contains".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                Ok(FunctionCallResult::Value(Value::Number(if index_of(&args, &spans)?.is_some() { 1.0 } else { 0.0 })))
+            }),
+        },
+        Builtin {
+            name: "index_of".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                Ok(FunctionCallResult::Value(Value::Number(index_of(&args, &spans)?.map(|i| i as f64).unwrap_or(-1.0))))
+            }),
+        },
+        Builtin {
+            name: "replace".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 3)?;
+                let s = get_string(&args[0], &spans[1])?;
+                let from = get_string(&args[1], &spans[2])?;
+                let to = get_string(&args[2], &spans[3])?;
+                Ok(FunctionCallResult::Value(Value::String(s.replacen(&from, &to, 1))))
+            }),
+        },
+        Builtin {
+            name: "replace_all".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 3)?;
+                let s = get_string(&args[0], &spans[1])?;
+                let from = get_string(&args[1], &spans[2])?;
+                let to = get_string(&args[2], &spans[3])?;
+                Ok(FunctionCallResult::Value(Value::String(s.replace(&from, &to))))
+            }),
+        },
+        Builtin {
+            name: "repeat".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let s = get_string(&args[0], &spans[1])?;
+                let count = get_count(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(Value::String(repeat_string(&s, count, &spans[2])?)))
+            }),
+        },
+        Builtin {
+            name: "lines".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                let s = get_string(&args[0], &spans[1])?;
+                Ok(FunctionCallResult::Value(string_list(s.lines().map(|l| l.to_owned()))))
+            }),
+        },
+        Builtin {
+            name: "split_whitespace".to_owned(),
             function: Rc::new(|args, spans, _closure, _slash| {
                 verify_formal_args(&args, &spans, 1)?;
                 let s = get_string(&args[0], &spans[1])?;
-                Ok(FunctionCallResult::Value(Value::String(String::from(s.trim()))))
+                Ok(FunctionCallResult::Value(string_list(s.split_whitespace().map(|w| w.to_owned()))))
+            }),
+        },
+        Builtin {
+            name: "trim".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                Ok(FunctionCallResult::Value(Value::String(trim(&args, &spans, true, true)?)))
+            }),
+        },
+        Builtin {
+            name: "trim_start".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                Ok(FunctionCallResult::Value(Value::String(trim(&args, &spans, true, false)?)))
+            }),
+        },
+        Builtin {
+            name: "trim_end".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                Ok(FunctionCallResult::Value(Value::String(trim(&args, &spans, false, true)?)))
+            }),
+        },
+        Builtin {
+            name: "format".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                if args.is_empty() {
+                    return Err(SlashError::new(&spans[0], "Expected a template to format"));
+                }
+                let template = get_string(&args[0], &spans[1])?;
+                Ok(FunctionCallResult::Value(Value::String(format_template(&template, &args[1..], &spans[1])?)))
             }),
         },
         Builtin {
             name: "join".to_owned(),
             function: Rc::new(|args, spans, _closure, _slash| {
-                verify_formal_args(&args, &spans, 2)?;
+                let optional = verify_optional_formal_args(&args, &spans, 1, 1)?;
                 let l = get_list(&args[0], &spans[1])?;
-                let c = get_string(&args[1], &spans[2])?;
+                let c = match &optional[0] {
+                    Some(separator) => get_string(separator, &spans[2])?,
+                    None => String::new()
+                };
                 let mut s_vec = Vec::new();
                 for sv in l.borrow().iter() {
                     match sv {
@@ -290,6 +661,396 @@ pub fn add_builtin_to_closure(closure: &mut Closure) {
                 Ok(FunctionCallResult::Value(Value::String(s_vec.join(&c))))
             }),
         },
+        Builtin {
+            name: "chars".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                let s = get_string(&args[0], &spans[1])?;
+                Ok(FunctionCallResult::Value(string_list(s.chars().map(|c| c.to_string()))))
+            }),
+        },
+        Builtin {
+            name: "graphemes".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                let s = get_string(&args[0], &spans[1])?;
+                Ok(FunctionCallResult::Value(string_list(s.graphemes(true).map(|g| g.to_owned()))))
+            }),
+        },
+        Builtin {
+            name: "substring".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                let end = verify_optional_formal_args(&args, &spans, 2, 1)?.pop().unwrap();
+                get_string(&args[0], &spans[1])?;
+                Ok(FunctionCallResult::Value(args[0].slice(&args[1], &end.unwrap_or(Value::Null), &spans[0])?))
+            }),
+        },
+        Builtin {
+            name: "upper".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                Ok(FunctionCallResult::Value(Value::String(get_string(&args[0], &spans[1])?.to_uppercase())))
+            }),
+        },
+        Builtin {
+            name: "lower".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                Ok(FunctionCallResult::Value(Value::String(get_string(&args[0], &spans[1])?.to_lowercase())))
+            }),
+        },
+        Builtin {
+            name: "pad_left".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                Ok(FunctionCallResult::Value(Value::String(pad(&args, &spans, true)?)))
+            }),
+        },
+        Builtin {
+            name: "pad_right".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                Ok(FunctionCallResult::Value(Value::String(pad(&args, &spans, false)?)))
+            }),
+        },
+        Builtin {
+            name: "reverse".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                match &args[0] {
+                    Value::String(s) => Ok(FunctionCallResult::Value(Value::String(s.graphemes(true).rev().collect()))),
+                    Value::List(l) => Ok(FunctionCallResult::Value(Value::List(Rc::new(Container::new(l.borrow().iter().rev().cloned().collect()))))),
+                    _ => Err(invalid_type_with_expected(&spans[1], &args[0], "String or List"))
+                }
+            }),
+        },
+        Builtin {
+            name: "display_width".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                let s = get_string(&args[0], &spans[1])?;
+                Ok(FunctionCallResult::Value(Value::Number(s.width() as f64)))
+            }),
+        },
+        Builtin {
+            name: "re_compile".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                Ok(FunctionCallResult::Value(Value::Regex(get_regex(&args[0], &spans[1])?)))
+            }),
+        },
+        Builtin {
+            name: "re_match".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let regex = get_regex(&args[0], &spans[1])?;
+                let s = get_string(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(Value::Number(if regex.is_match(&s) { 1.0 } else { 0.0 })))
+            }),
+        },
+        Builtin {
+            name: "re_find_all".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let regex = get_regex(&args[0], &spans[1])?;
+                let s = get_string(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(string_list(regex.find_iter(&s).map(|m| m.as_str().to_owned()))))
+            }),
+        },
+        Builtin {
+            name: "re_captures".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let regex = get_regex(&args[0], &spans[1])?;
+                let s = get_string(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(regex.captures(&s).map(|c| captures_table(&regex, &c)).unwrap_or(Value::Null)))
+            }),
+        },
+        Builtin {
+            name: "re_replace".to_owned(),
+            function: Rc::new(|args, spans, closure, slash| {
+                verify_formal_args(&args, &spans, 3)?;
+                let regex = get_regex(&args[0], &spans[1])?;
+                let s = get_string(&args[1], &spans[2])?;
+                match &args[2] {
+                    Value::String(replacement) => Ok(FunctionCallResult::Value(Value::String(regex.replace_all(&s, replacement.as_str()).into_owned()))),
+                    Value::Function(_) => {
+                        // The function is called with the captures of each match and returns its replacement
+                        let mut replaced = String::new();
+                        let mut last = 0;
+                        for captures in regex.captures_iter(&s) {
+                            let whole = captures.get(0).unwrap();
+                            replaced.push_str(&s[last..whole.start()]);
+                            let replacement = call_function(&args[2], &spans[3], vec!(captures_table(&regex, &captures)), closure, slash)?;
+                            replaced.push_str(&get_string(&replacement, &spans[3])?);
+                            last = whole.end();
+                        }
+                        replaced.push_str(&s[last..]);
+                        Ok(FunctionCallResult::Value(Value::String(replaced)))
+                    }
+                    _ => Err(invalid_type_with_expected(&spans[3], &args[2], "String or Function"))
+                }
+            }),
+        },
+        Builtin {
+            name: "re_split".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let regex = get_regex(&args[0], &spans[1])?;
+                let s = get_string(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(string_list(regex.split(&s).map(|part| part.to_owned()))))
+            }),
+        },
+        Builtin {
+            name: "map".to_owned(),
+            function: Rc::new(|args, spans, closure, slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let l = get_list(&args[0], &spans[1])?.borrow().clone();
+                let mut res = Vec::new();
+                for e in l {
+                    res.push(call_function(&args[1], &spans[2], vec!(e), closure, slash)?);
+                }
+                Ok(FunctionCallResult::Value(Value::List(Rc::new(Container::new(res)))))
+            }),
+        },
+        Builtin {
+            name: "filter".to_owned(),
+            function: Rc::new(|args, spans, closure, slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let l = get_list(&args[0], &spans[1])?.borrow().clone();
+                let mut res = Vec::new();
+                for e in l {
+                    if call_function(&args[1], &spans[2], vec!(e.clone()), closure, slash)?.is_true() {
+                        res.push(e);
+                    }
+                }
+                Ok(FunctionCallResult::Value(Value::List(Rc::new(Container::new(res)))))
+            }),
+        },
+        Builtin {
+            name: "flat_map".to_owned(),
+            function: Rc::new(|args, spans, closure, slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let l = get_list(&args[0], &spans[1])?.borrow().clone();
+                let mut res = Vec::new();
+                for e in l {
+                    let mapped = call_function(&args[1], &spans[2], vec!(e), closure, slash)?;
+                    res.extend(get_list(&mapped, &spans[2])?.borrow().iter().cloned());
+                }
+                Ok(FunctionCallResult::Value(Value::List(Rc::new(Container::new(res)))))
+            }),
+        },
+        Builtin {
+            name: "reduce".to_owned(),
+            function: Rc::new(|args, spans, closure, slash| {
+                let optional = verify_optional_formal_args(&args, &spans, 2, 1)?;
+                let mut l = get_list(&args[0], &spans[1])?.borrow().clone().into_iter();
+                let mut acc = match &optional[0] {
+                    Some(initial) => initial.clone(),
+                    None => l.next().ok_or_else(|| SlashError::new(&spans[1], "Cannot reduce an empty list without an initial value"))?
+                };
+                for e in l {
+                    acc = call_function(&args[1], &spans[2], vec!(acc, e), closure, slash)?;
+                }
+                Ok(FunctionCallResult::Value(acc))
+            }),
+        },
+        Builtin {
+            name: "any".to_owned(),
+            function: Rc::new(|args, spans, closure, slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let l = get_list(&args[0], &spans[1])?.borrow().clone();
+                for e in l {
+                    if call_function(&args[1], &spans[2], vec!(e), closure, slash)?.is_true() {
+                        return Ok(FunctionCallResult::Value(Value::Number(1.0)));
+                    }
+                }
+                Ok(FunctionCallResult::Value(Value::Number(0.0)))
+            }),
+        },
+        Builtin {
+            name: "all".to_owned(),
+            function: Rc::new(|args, spans, closure, slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let l = get_list(&args[0], &spans[1])?.borrow().clone();
+                for e in l {
+                    if !call_function(&args[1], &spans[2], vec!(e), closure, slash)?.is_true() {
+                        return Ok(FunctionCallResult::Value(Value::Number(0.0)));
+                    }
+                }
+                Ok(FunctionCallResult::Value(Value::Number(1.0)))
+            }),
+        },
+        Builtin {
+            name: "find".to_owned(),
+            function: Rc::new(|args, spans, closure, slash| {
+                let optional = verify_optional_formal_args(&args, &spans, 2, 1)?;
+                let l = get_list(&args[0], &spans[1])?.borrow().clone();
+                for e in l {
+                    if call_function(&args[1], &spans[2], vec!(e.clone()), closure, slash)?.is_true() {
+                        return Ok(FunctionCallResult::Value(e));
+                    }
+                }
+                match &optional[0] {
+                    Some(default) => Ok(FunctionCallResult::Value(default.clone())),
+                    None => Err(SlashError::new(&spans[0], "No element in the list matched and no default was given"))
+                }
+            }),
+        },
+        Builtin {
+            name: "sort_by".to_owned(),
+            function: Rc::new(|args, spans, closure, slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let l = get_list(&args[0], &spans[1])?.borrow().clone();
+                let mut keyed = Vec::new();
+                for e in l {
+                    let key = call_function(&args[1], &spans[2], vec!(e.clone()), closure, slash)?;
+                    if contains_nan(&key, &mut Vec::new()) {
+                        return Err(SlashError::new(&spans[2], &format!("Expected a key that can be sorted, but got {}", key.to_string())));
+                    }
+                    if let Some((first, _)) = keyed.first() {
+                        if !key.same_type(first) {
+                            return Err(SlashError::new(&spans[2], &format!("Expected keys of the same type, but got a {} and a {}", first.value_type(), key.value_type())));
+                        }
+                    }
+                    keyed.push((key, e));
+                }
+                let mut error = None;
+                keyed.sort_by(|(a, _), (b, _)| {
+                    match (a._less_than(b, &spans[2]), b._less_than(a, &spans[2])) {
+                        (Ok(true), _) => Ordering::Less,
+                        (Ok(false), Ok(true)) => Ordering::Greater,
+                        (Ok(false), Ok(false)) => Ordering::Equal,
+                        (Err(e), _) | (_, Err(e)) => {
+                            error.get_or_insert(e);
+                            Ordering::Equal
+                        }
+                    }
+                });
+                if let Some(e) = error {
+                    return Err(e);
+                }
+                Ok(FunctionCallResult::Value(Value::List(Rc::new(Container::new(keyed.into_iter().map(|(_, e)| e).collect())))))
+            }),
+        },
+        Builtin {
+            name: "group_by".to_owned(),
+            function: Rc::new(|args, spans, closure, slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let l = get_list(&args[0], &spans[1])?.borrow().clone();
+                let mut groups: IndexMap<String, Value> = IndexMap::new();
+                for e in l {
+                    let key = call_function(&args[1], &spans[2], vec!(e.clone()), closure, slash)?.to_string();
+                    let group = groups.entry(key).or_insert_with(|| Value::List(Rc::new(Container::new(Vec::new()))));
+                    group.push(e, &spans[2])?;
+                }
+                Ok(FunctionCallResult::Value(Value::Table(Rc::new(Container::new(groups)))))
+            }),
+        },
+        Builtin {
+            name: "take".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let iter = to_iterator(&args[0], &spans[1])?;
+                let n = get_count(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(ValueIterator::Take(iter, n).into_value()))
+            }),
+        },
+        Builtin {
+            name: "skip".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let iter = to_iterator(&args[0], &spans[1])?;
+                let n = get_count(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(ValueIterator::Skip(iter, n).into_value()))
+            }),
+        },
+        Builtin {
+            name: "zip".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let first = to_iterator(&args[0], &spans[1])?;
+                let second = to_iterator(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(ValueIterator::Zip(first, second).into_value()))
+            }),
+        },
+        Builtin {
+            name: "enumerate".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                let iter = to_iterator(&args[0], &spans[1])?;
+                Ok(FunctionCallResult::Value(ValueIterator::Enumerate(iter, 0).into_value()))
+            }),
+        },
+        Builtin {
+            name: "chain".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                let first = to_iterator(&args[0], &spans[1])?;
+                let second = to_iterator(&args[1], &spans[2])?;
+                Ok(FunctionCallResult::Value(ValueIterator::Chain(first, second).into_value()))
+            }),
+        },
+        Builtin {
+            name: "collect".to_owned(),
+            function: Rc::new(|args, spans, _closure, slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                let iter = to_iterator(&args[0], &spans[1])?;
+                let mut res = Vec::new();
+                while let Some(value) = next_value(&iter, &spans[1], slash)? {
+                    res.push(value);
+                }
+                Ok(FunctionCallResult::Value(Value::List(Rc::new(Container::new(res)))))
+            }),
+        },
+        Builtin {
+            name: "entries".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                let t = get_table(&args[0], &spans[1])?;
+                let entries = t.borrow().iter()
+                    .map(|(k, v)| Value::List(Rc::new(Container::new(vec!(Value::String(k.clone()), v.clone())))))
+                    .collect();
+                Ok(FunctionCallResult::Value(Value::List(Rc::new(Container::new(entries)))))
+            }),
+        },
+        Builtin {
+            name: "copy".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                Ok(FunctionCallResult::Value(args[0].copy()))
+            }),
+        },
+        Builtin {
+            name: "deep_copy".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                Ok(FunctionCallResult::Value(args[0].deep_copy()))
+            }),
+        },
+        Builtin {
+            name: "same".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 2)?;
+                Ok(FunctionCallResult::Value(Value::Number(if args[0].same(&args[1]) { 1.0 } else { 0.0 })))
+            }),
+        },
+        Builtin {
+            name: "freeze".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                verify_formal_args(&args, &spans, 1)?;
+                args[0].freeze();
+                Ok(FunctionCallResult::Value(args[0].clone()))
+            }),
+        },
+        Builtin {
+            name: "get".to_owned(),
+            function: Rc::new(|args, spans, _closure, _slash| {
+                let default = verify_optional_formal_args(&args, &spans, 2, 1)?.pop().unwrap();
+                match args[0].optional_lookup_by_index(&args[1], &spans[2])? {
+                    Value::Null => Ok(FunctionCallResult::Value(default.unwrap_or(Value::Null))),
+                    value => Ok(FunctionCallResult::Value(value))
+                }
+            }),
+        },
         Builtin {
             name: "path_of_script".to_owned(),
             function: Rc::new(|args, spans, _closure, slash| {
@@ -303,7 +1064,7 @@ pub fn add_builtin_to_closure(closure: &mut Closure) {
             name: "args".to_owned(),
             function: Rc::new(|args, spans, _closure, slash| {
                 verify_formal_args(&args, &spans, 0)?;
-                Ok(FunctionCallResult::Value(Value::List(Rc::new(RefCell::new(slash.args.iter().map(|s| Value::String(s.clone())).collect())))))
+                Ok(FunctionCallResult::Value(Value::List(Rc::new(Container::new(slash.args.iter().map(|s| Value::String(s.clone())).collect())))))
             }),
         },
         Builtin {
@@ -335,51 +1096,138 @@ pub fn add_builtin_to_closure(closure: &mut Closure) {
                         JsonValue::Short(s) => Value::String(s.as_str().to_owned()),
                         JsonValue::String(s) => Value::String(s.clone()),
                         JsonValue::Boolean(b) => Value::Number(if *b {1.0} else {0.0}),
-                        JsonValue::Null => Value::Table(Rc::new(RefCell::new(HashMap::new()))),
-                        JsonValue::Array(v) => Value::List(Rc::new(RefCell::new(v.iter().map(|e| json_value_to_slash_value(e)).collect()))),
+                        JsonValue::Null => Value::Null,
+                        JsonValue::Array(v) => Value::List(Rc::new(Container::new(v.iter().map(|e| json_value_to_slash_value(e)).collect()))),
                         JsonValue::Object(t) => {
-                            let mut m = HashMap::new();
+                            let mut m = IndexMap::new();
                             t.iter().for_each(|n| {
                                 m.insert(n.0.to_owned(), json_value_to_slash_value(n.1));
                             });
-                            Value::Table(Rc::new(RefCell::new(m)))
+                            Value::Table(Rc::new(Container::new(m)))
                         }
                     }
                 }
-                verify_formal_args(&args, &spans, 1)?;
+                // Parses the objects of the JSON, or the objects of a JSON array, into records of the type
+                fn to_records(value: Value, record_type: &Rc<RecordType>, span: &Span) -> Result<Value, SlashError> {
+                    match value {
+                        Value::Table(t) => RecordType::from_table(record_type, &t.borrow(), span),
+                        Value::List(l) => {
+                            let records = l.borrow().iter().map(|v| to_records(v.clone(), record_type, span)).collect::<Result<Vec<_>, _>>()?;
+                            Ok(Value::List(Rc::new(Container::new(records))))
+                        }
+                        _ => Err(SlashError::new(span, &format!("Expected a JSON object to parse into a {}, but got a {}", record_type.name, value.value_type())))
+                    }
+                }
+                let record_type = verify_optional_formal_args(&args, &spans, 1, 1)?.pop().unwrap();
                 let json = get_string(&args[0], &spans[1])?;
                 let j = json::parse(&json);
 
-                match j {
-                    Ok(v) => Ok(FunctionCallResult::Value(json_value_to_slash_value(&v))),
-                    Err(e) => Err(SlashError::new(&spans[0], &e.to_string()))
+                let value = match j {
+                    Ok(v) => json_value_to_slash_value(&v),
+                    Err(e) => return Err(SlashError::new(&spans[0], &e.to_string()))
+                };
+                match record_type {
+                    None => Ok(FunctionCallResult::Value(value)),
+                    Some(Value::Function(Function::Constructor(record_type))) => Ok(FunctionCallResult::Value(to_records(value, &record_type, &spans[1])?)),
+                    Some(value) => Err(invalid_type_with_expected(&spans[2], &value, "record type"))
                 }
             }),
         },
-    ).iter().for_each(|bi| closure.declare(&bi.name, Value::Function(Function::Builtin(bi.clone()))));
+    ).iter().for_each(|bi| closure.declare_builtin(&bi.name, Value::Function(Function::Builtin(bi.clone()))));
 }
 
 impl Function {
+    // Functions are the same when they come from the same evaluation of a declaration or lambda
+    pub fn same(&self, other: &Function) -> bool {
+        match (self, other) {
+            (Function::Builtin(lhs), Function::Builtin(rhs)) => Rc::ptr_eq(&lhs.function, &rhs.function),
+            (Function::User(lhs, ..), Function::User(rhs, ..)) => Rc::ptr_eq(lhs, rhs),
+            (Function::Constructor(lhs), Function::Constructor(rhs)) => Rc::ptr_eq(lhs, rhs),
+            _ => false
+        }
+    }
+
     pub fn invoke(&self, name: &str, args: Vec<Value>, spans: Vec<Span>, closure: &mut Closure, slash: &Slash) -> Result<FunctionCallResult, SlashError> {
+        self.invoke_with_named(name, args, spans, vec!(), closure, slash)
+    }
+
+    pub fn invoke_with_named(&self, name: &str, args: Vec<Value>, spans: Vec<Span>, named: Vec<NamedArg>, closure: &mut Closure, slash: &Slash) -> Result<FunctionCallResult, SlashError> {
         match self {
-            Function::Builtin(b) => (b.function)(args,spans,closure,slash),
-            Function::User(formal_args, body, closure) => {
-                if args.len() != formal_args.len() {
+            Function::Builtin(b) => {
+                if let Some(named_arg) = named.first() {
+                    return Err(SlashError::new(&named_arg.span, &format!("Builtin function {} does not accept named arguments", b.name)));
+                }
+                (b.function)(args,spans,closure,slash)
+            }
+            Function::Constructor(record_type) => Ok(FunctionCallResult::Value(RecordType::construct(record_type, args, &spans, named)?)),
+            Function::User(signature, body, closure) => {
+                let formal_args = &signature.args;
+                let positional = formal_args.iter().take_while(|a| !a.rest).count();
+                if positional == formal_args.len() && args.len() > positional {
                     return Err(SlashError::new(&spans[0], &format!("Parameter mismatch for function call {}, expected {} arguments but got {}", name, formal_args.len(), args.len())));
                 }
 
+                let mut bound: Vec<Option<Value>> = vec!(None; formal_args.len());
+                let mut rest = Vec::new();
+                for (i, arg) in args.into_iter().enumerate() {
+                    if i < positional { bound[i] = Some(arg) } else { rest.push(arg) }
+                }
+                for named_arg in named {
+                    match formal_args.iter().position(|a| a.name == named_arg.name && !a.rest) {
+                        None => return Err(SlashError::new(&named_arg.span, &format!("Function {} has no parameter named {}", name, named_arg.name))),
+                        Some(i) if bound[i].is_some() => return Err(SlashError::new(&named_arg.span, &format!("Parameter {} of function {} is given more than once", named_arg.name, name))),
+                        Some(i) => bound[i] = Some(named_arg.value)
+                    }
+                }
+
+                // Defaults are evaluated at call time and can refer to the parameters before them
                 let mut execution_closure = closure.derived();
-                for i in 0..args.len() {
-                    execution_closure.declare(&formal_args[i][..], args[i].clone());
+                for (formal_arg, value) in formal_args.iter().zip(bound) {
+                    let value = match (value, &formal_arg.default) {
+                        _ if formal_arg.rest => Value::List(Rc::new(Container::new(std::mem::take(&mut rest)))),
+                        (Some(value), _) => value,
+                        (None, Some(default)) => {
+                            let mut pairs = crate::SlashParser::parse(Rule::value_expression, default).unwrap();
+                            evaluate_to_value(pairs.next().unwrap(), &mut execution_closure, slash)?
+                        }
+                        (None, None) => return Err(SlashError::new(&spans[0], &format!("Missing argument {} for function call {}", formal_arg.name, name)))
+                    };
+                    if let Some(annotation) = &formal_arg.annotation {
+                        let what = format!("argument {} of function {}", formal_arg.name, name);
+                        match &value {
+                            Value::List(rest) if formal_arg.rest => {
+                                rest.borrow().iter().try_for_each(|v| check_type(v, annotation, &what, &spans[0]))?
+                            }
+                            value => check_type(value, annotation, &what, &spans[0])?
+                        }
+                    }
+                    execution_closure.declare(&formal_arg.name, value).map_err(|e| SlashError::new(&spans[0], &e))?;
                 }
 
-                let mut pairs = crate::SlashParser::parse(Rule::block, &body).unwrap();
-                let res = slash.execute(pairs.next().unwrap(), &mut execution_closure)?;
-                if let ExecuteResult::Return(v, _) = res {
-                    Ok(FunctionCallResult::Value(v))
-                } else {
-                    Ok(FunctionCallResult::NoValue("".to_owned()))
+                let result = match body {
+                    FunctionBody::Block(body) => {
+                        let mut pairs = crate::SlashParser::parse(Rule::block, body).unwrap();
+                        let res = slash.execute(pairs.next().unwrap(), &mut execution_closure)?;
+                        match res {
+                            ExecuteResult::Return(v, _) => FunctionCallResult::Value(v),
+                            ExecuteResult::None => FunctionCallResult::NoValue("".to_owned()),
+                            res => return Err(res.unexpected_loop_control())
+                        }
+                    }
+                    FunctionBody::Generator(body) => {
+                        FunctionCallResult::Value(ValueIterator::Generator(Generator::new(body.clone(), execution_closure)).into_value())
+                    }
+                    FunctionBody::Expression(body) => {
+                        let mut pairs = crate::SlashParser::parse(Rule::value_expression, body).unwrap();
+                        FunctionCallResult::Value(evaluate_to_value(pairs.next().unwrap(), &mut execution_closure, slash)?)
+                    }
+                };
+                match (&signature.returns, &result) {
+                    (Some(returns), FunctionCallResult::Value(value)) => check_type(value, returns, &format!("the return value of function {}", name), &spans[0])?,
+                    (Some(returns), FunctionCallResult::NoValue(_)) => return Err(SlashError::new(&spans[0], &format!("Expected function {} to return a {}, but it returned no value", name, returns))),
+                    _ => {}
                 }
+                Ok(result)
             }
         }
     }
@@ -389,13 +1237,14 @@ pub fn function_call(pair: Pair<Rule>, closure: &mut Closure, slash: &Slash) ->
     let mut pairs = pair.into_inner();
     let function_pair = pairs.next().unwrap();
     let function_span = function_pair.as_span();
+    let function_name = function_pair.as_str();
     let args_pair = pairs.next().unwrap();
     let function = evaluate_to_value(function_pair, closure, slash)?;
-    let (args,mut spans) = evaluate_to_args(args_pair, closure, slash)?;
+    let (args, mut spans, named) = evaluate_call_args(args_pair, closure, slash)?;
 
     let mut func_spans = vec!(function_span);
     func_spans.append(&mut spans);
-    function.invoke(args,func_spans,closure, slash)
+    function.invoke(function_name, args, func_spans, named, closure, slash)
 }
 
 fn format_args(args: Vec<Value>) -> String {
@@ -421,6 +1270,15 @@ fn verify_formal_args(args: &Vec<Value>, spans: &Vec<Span>, num: usize) -> Resul
     }
 }
 
+// Verifies a builtin call with optional trailing arguments, missing optional arguments are returned as None
+fn verify_optional_formal_args(args: &[Value], spans: &[Span], required: usize, optional: usize) -> Result<Vec<Option<Value>>, SlashError> {
+    if args.len() < required || args.len() > required + optional {
+        Err(SlashError::new(&spans[0], &format!("Expected {} to {} arguments, but got {}", required, required + optional, args.len())))
+    } else {
+        Ok((required..required + optional).map(|i| args.get(i).cloned()).collect())
+    }
+}
+
 fn invalid_type(span: &Span, value: &Value) -> SlashError {
     SlashError::new(span, &format!("Type {} of value is invalid", value.value_type()))
 }
@@ -429,6 +1287,296 @@ fn invalid_type_with_expected(span: &Span, value: &Value, expected: &str) -> Sla
     SlashError::new(span, &format!("Expected value to be of type {} but it was of type {}", expected, value.value_type()))
 }
 
+// Calls a function value passed as an argument to a builtin, the function must return a value
+fn call_function(function: &Value, span: &Span, args: Vec<Value>, closure: &mut Closure, slash: &Slash) -> Result<Value, SlashError> {
+    let spans = vec!(span.clone(); args.len() + 1);
+    match function {
+        Value::Function(f) => match f.invoke("(expr)", args, spans, closure, slash)? {
+            FunctionCallResult::Value(v) => Ok(v),
+            FunctionCallResult::NoValue(_) => Err(SlashError::new(span, "Expected function to return a value"))
+        },
+        _ => Err(invalid_type_with_expected(span, function, "Function"))
+    }
+}
+
+// The position of a substring in a string, counted in characters, or of an element in a list
+fn index_of(args: &[Value], spans: &[Span]) -> Result<Option<usize>, SlashError> {
+    match &args[0] {
+        Value::String(s) => {
+            let sub = get_string(&args[1], &spans[2])?;
+            Ok(s.find(&sub).map(|byte_index| s[..byte_index].chars().count()))
+        }
+        Value::List(l) => {
+            for (i, element) in l.borrow().iter().enumerate() {
+                if element.same_type(&args[1]) && element._equals(&args[1], &spans[2])? {
+                    return Ok(Some(i));
+                }
+            }
+            Ok(None)
+        }
+        _ => Err(invalid_type_with_expected(&spans[1], &args[0], "String or List"))
+    }
+}
+
+// Trims whitespace, or the characters of an optional string of characters, from the start and or the end
+fn trim(args: &[Value], spans: &[Span], start: bool, end: bool) -> Result<String, SlashError> {
+    let chars = verify_optional_formal_args(args, spans, 1, 1)?.pop().unwrap();
+    let s = get_string(&args[0], &spans[1])?;
+    let trimmed = match chars {
+        Some(chars) => {
+            let chars: Vec<char> = get_string(&chars, &spans[2])?.chars().collect();
+            let s = if start { s.trim_start_matches(chars.as_slice()) } else { &s };
+            if end { s.trim_end_matches(chars.as_slice()) } else { s }.to_owned()
+        }
+        None => {
+            let s = if start { s.trim_start() } else { &s };
+            if end { s.trim_end() } else { s }.to_owned()
+        }
+    };
+    Ok(trimmed)
+}
+
+// Formats the arguments into a template. {} is replaced by the next argument and {n} by argument n, counting
+// from 0. A specifier after a colon, {:[[fill]align][0][width][.precision]}, formats the argument, align is
+// < (left), > (right) or ^ (center). {{ and }} are a literal { and }
+// The largest number of decimals a number can be formatted with
+const MAX_PRECISION: usize = u16::MAX as usize;
+
+fn format_template(template: &str, args: &[Value], span: &Span) -> Result<String, SlashError> {
+    let mut result = String::new();
+    let mut next_arg = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(SlashError::new(span, "Unclosed { in format template"))
+                    }
+                }
+                let (position, spec) = match placeholder.find(':') {
+                    Some(i) => (&placeholder[..i], Some(&placeholder[i + 1..])),
+                    None => (placeholder.as_str(), None)
+                };
+                let index = if position.is_empty() {
+                    next_arg += 1;
+                    next_arg - 1
+                } else {
+                    position.parse::<usize>().map_err(|_| SlashError::new(span, &format!("Invalid format placeholder {{{}}}", placeholder)))?
+                };
+                let arg = args.get(index).ok_or_else(|| SlashError::new(span, &format!("Missing argument {} for format, got {} arguments", index, args.len())))?;
+                match spec {
+                    Some(spec) => result.push_str(&format_value(arg, spec, span)?),
+                    None => result.push_str(&arg.to_string())
+                }
+            }
+            '}' => return Err(SlashError::new(span, "Unmatched } in format template, use }} for a literal }")),
+            c => result.push(c)
+        }
+    }
+    Ok(result)
+}
+
+fn format_value(value: &Value, spec: &str, span: &Span) -> Result<String, SlashError> {
+    let invalid = || SlashError::new(span, &format!("Invalid format specifier {}", spec));
+    let mut chars: Vec<char> = spec.chars().collect();
+    let is_align = |c: &char| matches!(c, '<' | '>' | '^');
+    let (fill, align) = match (chars.first(), chars.get(1)) {
+        (Some(fill), Some(align)) if is_align(align) => {
+            let fill_align = (*fill, Some(*align));
+            chars.drain(..2);
+            fill_align
+        }
+        (Some(align), _) if is_align(align) => {
+            let fill_align = (' ', Some(*align));
+            chars.remove(0);
+            fill_align
+        }
+        _ => (' ', None)
+    };
+    let zero = align.is_none() && chars.first() == Some(&'0');
+    if zero { chars.remove(0); }
+    let rest: String = chars.into_iter().collect();
+    let (width, precision) = match rest.find('.') {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest.as_str(), None)
+    };
+    let width = if width.is_empty() { 0 } else { width.parse::<usize>().map_err(|_| invalid())? };
+    let precision = match precision {
+        Some(precision) => Some(precision.parse::<usize>().map_err(|_| invalid())?),
+        None => None
+    };
+    if let Some(precision) = precision.filter(|p| *p > MAX_PRECISION) {
+        return Err(SlashError::new(span, &format!("Expected a precision of at most {}, but got {}", MAX_PRECISION, precision)));
+    }
+
+    let formatted = match (value, precision) {
+        (Value::Number(n), Some(precision)) => format!("{:.*}", precision, n),
+        (Value::String(s), Some(precision)) => s.chars().take(precision).collect(),
+        (value, _) => value.to_string()
+    };
+    let padding = width.saturating_sub(formatted.width());
+    if zero && matches!(value, Value::Number(_)) {
+        let (sign, digits) = if let Some(digits) = formatted.strip_prefix('-') { ("-", digits) } else { ("", formatted.as_str()) };
+        return Ok(format!("{}{}{}", sign, repeat_string("0", padding, span)?, digits));
+    }
+    let fill = |n: usize| repeat_string(&fill.to_string(), n, span);
+    // Numbers are aligned to the right by default, everything else to the left
+    Ok(match align.unwrap_or(if matches!(value, Value::Number(_)) { '>' } else { '<' }) {
+        '>' => fill(padding)? + &formatted,
+        '^' => fill(padding / 2)? + &formatted + &fill(padding - padding / 2)?,
+        _ => formatted + &fill(padding)?
+    })
+}
+
+// A string repeated count times, or an error when the result is too long to be built
+fn repeat_string(s: &str, count: usize, span: &Span) -> Result<String, SlashError> {
+    let mut repeated = String::new();
+    if s.is_empty() {
+        return Ok(repeated);
+    }
+    s.len().checked_mul(count)
+        .and_then(|len| repeated.try_reserve_exact(len).ok())
+        .ok_or_else(|| SlashError::new(span, &format!("Cannot repeat a string {} times, the result is too long", count)))?;
+    for _ in 0..count {
+        repeated.push_str(s);
+    }
+    Ok(repeated)
+}
+
+// A regular expression value, or a string that is compiled into one
+fn get_regex(arg: &Value, span: &Span) -> Result<Rc<Regex>, SlashError> {
+    match arg {
+        Value::Regex(regex) => Ok(regex.clone()),
+        Value::String(source) => Slash::compile_regex(source, span),
+        _ => Err(invalid_type_with_expected(span, arg, "Regex or String"))
+    }
+}
+
+// The groups of a match by their name, or by their number for groups without a name. Group 0 is the whole
+// match and a group that did not take part in the match is null
+fn captures_table(regex: &Regex, captures: &Captures) -> Value {
+    let mut table = IndexMap::new();
+    for (i, name) in regex.capture_names().enumerate() {
+        let key = name.map(|n| n.to_owned()).unwrap_or_else(|| i.to_string());
+        let value = captures.get(i).map(|m| Value::String(m.as_str().to_owned())).unwrap_or(Value::Null);
+        table.insert(key, value);
+    }
+    Value::Table(Rc::new(Container::new(table)))
+}
+
+fn string_list(strings: impl Iterator<Item=String>) -> Value {
+    Value::List(Rc::new(Container::new(strings.map(Value::String).collect())))
+}
+
+// Pads a string with a fill character, a space by default, until it is as wide as the given width in a terminal
+fn pad(args: &[Value], spans: &[Span], left: bool) -> Result<String, SlashError> {
+    let fill = verify_optional_formal_args(args, spans, 2, 1)?.pop().unwrap();
+    let s = get_string(&args[0], &spans[1])?;
+    let width = get_count(&args[1], &spans[2])?;
+    let fill = match fill {
+        Some(fill) => get_string(&fill, &spans[3])?,
+        None => " ".to_owned()
+    };
+    if fill.chars().count() != 1 || fill.width() == 0 {
+        return Err(SlashError::new(&spans[3], &format!("Expected a single fill character, but got \"{}\"", fill)));
+    }
+    let padding = repeat_string(&fill, width.saturating_sub(s.width()) / fill.width(), &spans[2])?;
+    Ok(if left { padding + &s } else { s + &padding })
+}
+
+// Applies a function of one number to the argument
+fn math(args: &[Value], spans: &[Span], f: fn(f64) -> f64) -> Result<FunctionCallResult, SlashError> {
+    if args.len() != 1 {
+        return Err(SlashError::new(&spans[0], &format!("Expected 1 arguments, but got {}", args.len())));
+    }
+    Ok(FunctionCallResult::Value(Value::Number(f(get_number(&args[0], &spans[1])?))))
+}
+
+// Whether a sort key is or contains NaN, which is neither less than nor greater than any number
+fn contains_nan(key: &Value, visited: &mut Vec<usize>) -> bool {
+    match key {
+        Value::Number(n) => n.is_nan(),
+        Value::List(l) => {
+            let address = key.address().unwrap();
+            if visited.contains(&address) { return false; }
+            visited.push(address);
+            l.borrow().iter().any(|e| contains_nan(e, visited))
+        }
+        _ => false
+    }
+}
+
+// The least or greatest of the arguments, or of the elements of a list if it is the only argument
+fn extreme(args: Vec<Value>, spans: &[Span], wanted: Ordering) -> Result<Value, SlashError> {
+    let values = match args.as_slice() {
+        [Value::List(l)] => l.borrow().clone(),
+        _ => args
+    };
+    if let Some(nan) = values.iter().find(|v| contains_nan(v, &mut Vec::new())) {
+        return Err(SlashError::new(&spans[0], &format!("Expected values that can be compared, but got {}", nan.to_string())));
+    }
+    let mut values = values.into_iter();
+    let mut result = values.next().ok_or_else(|| SlashError::new(&spans[0], "Expected at least one value"))?;
+    for value in values {
+        let better = match wanted {
+            Ordering::Less => value._less_than(&result, &spans[0])?,
+            _ => result._less_than(&value, &spans[0])?
+        };
+        if better { result = value; }
+    }
+    Ok(result)
+}
+
+// Inserts a separator between each group of three digits of the integer part of a formatted number
+fn group_thousands(formatted: &str, separator: &str) -> String {
+    let (sign, unsigned) = match formatted.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", formatted)
+    };
+    let (integer, fraction) = match unsigned.find('.') {
+        Some(i) => unsigned.split_at(i),
+        None => (unsigned, "")
+    };
+    if !integer.chars().all(|c| c.is_ascii_digit()) {
+        return formatted.to_owned();
+    }
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    format!("{}{}{}", sign, grouped, fraction)
+}
+
+fn get_number(arg: &Value, span: &Span) -> Result<f64, SlashError> {
+    match arg {
+        Value::Number(n) => Ok(*n),
+        _ => Err(invalid_type_with_expected(span, arg, "Number"))
+    }
+}
+
+fn get_count(arg: &Value, span: &Span) -> Result<usize, SlashError> {
+    match arg {
+        Value::Number(n) if *n >= 0.0 => Ok(*n as usize),
+        Value::Number(n) => Err(SlashError::new(span, &format!("Expected a count that is not negative, but got {}", n))),
+        _ => Err(invalid_type_with_expected(span, arg, "Number"))
+    }
+}
+
 fn get_string(arg: &Value, span: &Span) -> Result<String, SlashError> {
     match arg {
         Value::String(s) => Ok(s.clone()),
@@ -436,10 +1584,16 @@ fn get_string(arg: &Value, span: &Span) -> Result<String, SlashError> {
     }
 }
 
-fn get_list(arg: &Value, span: &Span) -> Result<Rc<RefCell<Vec<Value>>>, SlashError> {
+fn get_list(arg: &Value, span: &Span) -> Result<Rc<Container<Vec<Value>>>, SlashError> {
     match arg {
         Value::List(l) => Ok(l.clone()),
         _ => Err(invalid_type_with_expected(span, arg, "List"))
     }
 }
 
+fn get_table(arg: &Value, span: &Span) -> Result<Rc<Container<IndexMap<String, Value>>>, SlashError> {
+    match arg {
+        Value::Table(t) => Ok(t.clone()),
+        _ => Err(invalid_type_with_expected(span, arg, "Table"))
+    }
+}
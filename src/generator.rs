@@ -0,0 +1,301 @@
+// Generators are user functions with a yield statement in their body. Calling one returns an iterator,
+// and each time the iterator is advanced the body runs until the next yield. The body is parsed again
+// on every resume, so the position of the execution is kept as a stack of frames, one for each nested
+// statement that contains a yield, holding the index of the next statement and the scopes.
+
+use pest::iterators::Pair;
+use pest::Span;
+use pest::Parser;
+use crate::{Rule, Slash, SlashParser, ExecuteResult};
+use crate::closure::Closure;
+use crate::value::Value;
+use crate::error::SlashError;
+use crate::evaluate::{evaluate_to_value, evaluate_to_iterable};
+use crate::iterator::ForInIterator;
+use crate::pattern::destructure;
+
+enum Frame {
+    Statements { index: usize, closure: Closure },
+    If { branch: usize },
+    Loop,
+    ForIn { items: ForInIterator, closure: Closure },
+    ForStd { closure: Closure },
+    Match { arm: usize, closure: Closure },
+}
+
+enum Flow<'a> {
+    Yield(Value),
+    Done,
+    Break(Span<'a>, Option<&'a str>),
+    Continue(Span<'a>, Option<&'a str>),
+    Return,
+}
+
+pub struct Generator {
+    body: String,
+    closure: Closure,
+    frames: Vec<Frame>,
+    done: bool,
+}
+
+pub fn contains_yield(pair: &Pair<Rule>) -> bool {
+    pair.clone().into_inner().any(|p| match p.as_rule() {
+        Rule::yield_statement => true,
+        Rule::function_declaration | Rule::anonymous_function => false,
+        _ => contains_yield(&p)
+    })
+}
+
+impl Generator {
+    pub fn new(body: String, closure: Closure) -> Generator {
+        Generator { body, closure, frames: Vec::new(), done: false }
+    }
+
+    pub fn resume(&mut self, slash: &Slash) -> Result<Option<Value>, SlashError> {
+        if self.done { return Ok(None); }
+        let body = self.body.clone();
+        let block = SlashParser::parse(Rule::block, &body).unwrap().next().unwrap();
+        let closure = self.closure.clone();
+        match self.run_statements(block.into_inner().collect(), 0, &closure, slash) {
+            Ok(Flow::Yield(value)) => Ok(Some(value)),
+            Ok(Flow::Break(span, label)) => {
+                self.done = true;
+                self.frames.clear();
+                Err(ExecuteResult::Break(span, label).unexpected_loop_control())
+            }
+            Ok(Flow::Continue(span, label)) => {
+                self.done = true;
+                self.frames.clear();
+                Err(ExecuteResult::Continue(span, label).unexpected_loop_control())
+            }
+            result => {
+                self.done = true;
+                self.frames.clear();
+                result.map(|_| None)
+            }
+        }
+    }
+
+    fn run_statements<'a>(&mut self, statements: Vec<Pair<'a, Rule>>, depth: usize, closure: &Closure, slash: &Slash) -> Result<Flow<'a>, SlashError> {
+        if self.frames.len() == depth {
+            self.frames.push(Frame::Statements { index: 0, closure: closure.derived() });
+        }
+        loop {
+            let (index, mut scope) = match &self.frames[depth] {
+                Frame::Statements { index, closure } => (*index, closure.clone()),
+                _ => unreachable!()
+            };
+            let statement = match statements.get(index) {
+                Some(statement) => statement.clone(),
+                None => break
+            };
+
+            let flow = if statement.as_rule() == Rule::yield_statement {
+                let value = evaluate_to_value(statement.into_inner().next().unwrap(), &mut scope, slash)?;
+                self.frames[depth] = Frame::Statements { index: index + 1, closure: scope };
+                return Ok(Flow::Yield(value));
+            } else if contains_yield(&statement) {
+                self.run_statement(statement, depth + 1, &mut scope, slash)?
+            } else {
+                match slash.execute(statement, &mut scope)? {
+                    ExecuteResult::None => Flow::Done,
+                    ExecuteResult::Break(span, label) => Flow::Break(span, label),
+                    ExecuteResult::Continue(span, label) => Flow::Continue(span, label),
+                    ExecuteResult::Return(..) => Flow::Return,
+                }
+            };
+
+            match flow {
+                Flow::Done => self.frames[depth] = Frame::Statements { index: index + 1, closure: scope },
+                flow => return Ok(self.leave(depth, flow)),
+            }
+        }
+        Ok(self.leave(depth, Flow::Done))
+    }
+
+    fn run_statement<'a>(&mut self, statement: Pair<'a, Rule>, depth: usize, closure: &mut Closure, slash: &Slash) -> Result<Flow<'a>, SlashError> {
+        match statement.as_rule() {
+            Rule::block => self.run_statements(statement.into_inner().collect(), depth, closure, slash),
+            Rule::while_statement | Rule::do_while_statement | Rule::for_in_statement | Rule::for_std_statement => {
+                self.run_loop(statement, None, depth, closure, slash)
+            }
+            Rule::labeled_loop => {
+                let mut pairs = statement.into_inner();
+                let label = pairs.next().unwrap().as_str();
+                self.run_loop(pairs.next().unwrap(), Some(label), depth, closure, slash)
+            }
+            Rule::if_statement => {
+                let branches: Vec<_> = statement.into_inner().collect();
+                if self.frames.len() == depth {
+                    let mut i = 0;
+                    while i < branches.len() && branches[i].as_rule() == Rule::expression {
+                        if evaluate_to_value(branches[i].clone(), closure, slash)?.is_true() { break; }
+                        i += 2;
+                    }
+                    let branch = if i < branches.len() && branches[i].as_rule() == Rule::expression { i + 1 } else { i };
+                    if branch >= branches.len() { return Ok(Flow::Done); }
+                    self.frames.push(Frame::If { branch });
+                }
+                let branch = if let Frame::If { branch } = self.frames[depth] { branch } else { unreachable!() };
+                let flow = self.run_branch(branches[branch].clone(), depth + 1, closure, slash)?;
+                Ok(self.leave(depth, flow))
+            }
+            Rule::match_statement => {
+                let mut pairs = statement.into_inner();
+                let expression = pairs.next().unwrap();
+                let arms: Vec<_> = pairs.collect();
+                if self.frames.len() == depth {
+                    let value = evaluate_to_value(expression, closure, slash)?;
+                    let mut matched = None;
+                    for (arm, match_arm) in arms.iter().enumerate() {
+                        let mut patterns: Vec<_> = match_arm.clone().into_inner().collect();
+                        patterns.pop();
+                        let guard = if matches!(patterns.last(), Some(p) if p.as_rule() == Rule::match_guard) { patterns.pop() } else { None };
+                        if let Some(arm_closure) = slash.matches(&value, patterns, guard, closure)? {
+                            matched = Some(Frame::Match { arm, closure: arm_closure });
+                            break;
+                        }
+                    }
+                    match matched {
+                        Some(frame) => self.frames.push(frame),
+                        None => return Ok(Flow::Done)
+                    }
+                }
+                let (arm, scope) = if let Frame::Match { arm, closure } = &self.frames[depth] { (*arm, closure.clone()) } else { unreachable!() };
+                let block = arms[arm].clone().into_inner().last().unwrap();
+                let flow = self.run_statements(block.into_inner().collect(), depth + 1, &scope, slash)?;
+                Ok(self.leave(depth, flow))
+            }
+            _ => unreachable!("{:?}", statement.as_rule())
+        }
+    }
+
+    // Runs a while, do-while, for-in or for loop. The label is given when the loop is labeled
+    fn run_loop<'a>(&mut self, statement: Pair<'a, Rule>, label: Option<&str>, depth: usize, closure: &mut Closure, slash: &Slash) -> Result<Flow<'a>, SlashError> {
+        match statement.as_rule() {
+        Rule::while_statement => {
+            let mut pairs = statement.into_inner();
+            let condition = pairs.next().unwrap();
+            let body = pairs.next().unwrap();
+            if self.frames.len() == depth {
+                self.frames.push(Frame::Loop);
+            }
+            loop {
+                if self.frames.len() == depth + 1 && !evaluate_to_value(condition.clone(), closure, slash)?.is_true() {
+                    break;
+                }
+                if let Some(flow) = self.run_loop_body(body.clone(), label, depth + 1, closure, slash)? {
+                    return Ok(self.leave(depth, flow));
+                }
+            }
+            Ok(self.leave(depth, Flow::Done))
+        }
+        Rule::do_while_statement => {
+            let mut pairs = statement.into_inner();
+            let body = pairs.next().unwrap();
+            let condition = pairs.next().unwrap();
+            if self.frames.len() == depth {
+                self.frames.push(Frame::Loop);
+            }
+            loop {
+                if let Some(flow) = self.run_loop_body(body.clone(), label, depth + 1, closure, slash)? {
+                    return Ok(self.leave(depth, flow));
+                }
+                if !evaluate_to_value(condition.clone(), closure, slash)?.is_true() {
+                    break;
+                }
+            }
+            Ok(self.leave(depth, Flow::Done))
+        }
+        Rule::for_in_statement => {
+            let mut pairs: Vec<_> = statement.into_inner().collect();
+            let body = pairs.pop().unwrap();
+            let expression = pairs.pop().unwrap();
+            let span = expression.as_span();
+            let value_pattern = pairs.pop().unwrap();
+            let key_pattern = pairs.pop();
+            if self.frames.len() == depth {
+                let items = ForInIterator::new(evaluate_to_iterable(expression, closure, slash)?, key_pattern.is_some(), &span)?;
+                self.frames.push(Frame::ForIn { items, closure: closure.derived() });
+            }
+            loop {
+                let mut scope = if let Frame::ForIn { closure, .. } = &self.frames[depth] { closure.clone() } else { unreachable!() };
+                if self.frames.len() == depth + 1 {
+                    let item = match &mut self.frames[depth] {
+                        Frame::ForIn { items, .. } => items.next(&span, slash)?,
+                        _ => unreachable!()
+                    };
+                    match item {
+                        None => break,
+                        Some((key, value)) => {
+                            if let Some(key_pattern) = &key_pattern {
+                                destructure(key_pattern.clone(), key, &mut scope, slash)?;
+                            }
+                            destructure(value_pattern.clone(), value, &mut scope, slash)?;
+                        }
+                    }
+                }
+                if let Some(flow) = self.run_loop_body(body.clone(), label, depth + 1, &scope, slash)? {
+                    return Ok(self.leave(depth, flow));
+                }
+            }
+            Ok(self.leave(depth, Flow::Done))
+        }
+        Rule::for_std_statement => {
+            let mut pairs = statement.into_inner();
+            let var_pair = pairs.next().unwrap();
+            let var_name = var_pair.as_str();
+            let init_expression = pairs.next().unwrap();
+            let continue_expression = pairs.next().unwrap();
+            let update_assignment = pairs.next().unwrap();
+            let body = pairs.next().unwrap();
+            if self.frames.len() == depth {
+                let mut scope = closure.derived();
+                let value = evaluate_to_value(init_expression, &mut scope, slash)?;
+                scope.declare(var_name, value).map_err(|e| SlashError::new(&var_pair.as_span(), &e))?;
+                self.frames.push(Frame::ForStd { closure: scope });
+            }
+            let mut scope = if let Frame::ForStd { closure } = &self.frames[depth] { closure.clone() } else { unreachable!() };
+            loop {
+                if self.frames.len() == depth + 1 && !evaluate_to_value(continue_expression.clone(), &mut scope, slash)?.is_true() {
+                    break;
+                }
+                if let Some(flow) = self.run_loop_body(body.clone(), label, depth + 1, &scope, slash)? {
+                    return Ok(self.leave(depth, flow));
+                }
+                slash.update_loop_variable(var_name, update_assignment.clone(), &mut scope)?;
+            }
+            Ok(self.leave(depth, Flow::Done))
+        }
+            _ => unreachable!("{:?}", statement.as_rule())
+        }
+    }
+
+    // A branch of an if statement is either a block or a single statement
+    fn run_branch<'a>(&mut self, branch: Pair<'a, Rule>, depth: usize, closure: &Closure, slash: &Slash) -> Result<Flow<'a>, SlashError> {
+        if branch.as_rule() == Rule::block {
+            self.run_statements(branch.into_inner().collect(), depth, closure, slash)
+        } else {
+            self.run_statements(vec!(branch), depth, closure, slash)
+        }
+    }
+
+    // Runs the body of a loop, returns the flow that ends the loop if the loop should not continue
+    fn run_loop_body<'a>(&mut self, body: Pair<'a, Rule>, label: Option<&str>, depth: usize, closure: &Closure, slash: &Slash) -> Result<Option<Flow<'a>>, SlashError> {
+        match self.run_statements(body.into_inner().collect(), depth, closure, slash)? {
+            Flow::Done | Flow::Continue(_, None) => Ok(None),
+            Flow::Continue(_, Some(l)) if Some(l) == label => Ok(None),
+            Flow::Break(_, None) => Ok(Some(Flow::Done)),
+            Flow::Break(_, Some(l)) if Some(l) == label => Ok(Some(Flow::Done)),
+            flow => Ok(Some(flow))
+        }
+    }
+
+    // Removes the frames of a statement when it is left, a yield keeps them for the resume
+    fn leave<'a>(&mut self, depth: usize, flow: Flow<'a>) -> Flow<'a> {
+        if !matches!(flow, Flow::Yield(_)) {
+            self.frames.truncate(depth);
+        }
+        flow
+    }
+}
@@ -0,0 +1,142 @@
+// Lazy iterators used by for-in loops, generators and the iterator builtins
+
+use crate::value::{Value, Container};
+use crate::generator::Generator;
+use crate::evaluate::Iterable;
+use crate::error::SlashError;
+use crate::Slash;
+use pest::Span;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fmt::{Debug, Formatter};
+use std::fmt;
+
+pub type Iter = Rc<RefCell<ValueIterator>>;
+
+pub enum ValueIterator {
+    Values(Vec<Value>, usize),
+    Range(f64, f64),
+    Generator(Generator),
+    Take(Iter, usize),
+    Skip(Iter, usize),
+    Zip(Iter, Iter),
+    Enumerate(Iter, usize),
+    Chain(Iter, Iter),
+}
+
+impl Debug for ValueIterator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("Iterator")
+    }
+}
+
+impl ValueIterator {
+    pub fn into_value(self) -> Value {
+        Value::Iterator(Rc::new(RefCell::new(self)))
+    }
+
+    fn next(&mut self, span: &Span, slash: &Slash) -> Result<Option<Value>, SlashError> {
+        use ValueIterator::*;
+        match self {
+            Values(values, index) => {
+                let value = values.get(*index).cloned();
+                *index += 1;
+                Ok(value)
+            }
+            Range(next, end) => {
+                if *next >= *end { return Ok(None); }
+                *next += 1.0;
+                Ok(Some(Value::Number(*next - 1.0)))
+            }
+            Generator(generator) => generator.resume(slash),
+            Take(iter, remaining) => {
+                if *remaining == 0 { return Ok(None); }
+                *remaining -= 1;
+                next_value(iter, span, slash)
+            }
+            Skip(iter, skip) => {
+                while *skip > 0 {
+                    *skip -= 1;
+                    if next_value(iter, span, slash)?.is_none() { return Ok(None); }
+                }
+                next_value(iter, span, slash)
+            }
+            Zip(first, second) => {
+                match (next_value(first, span, slash)?, next_value(second, span, slash)?) {
+                    (Some(a), Some(b)) => Ok(Some(Value::List(Rc::new(Container::new(vec!(a, b)))))),
+                    _ => Ok(None)
+                }
+            }
+            Enumerate(iter, index) => {
+                let value = next_value(iter, span, slash)?;
+                Ok(value.map(|v| {
+                    *index += 1;
+                    Value::List(Rc::new(Container::new(vec!(Value::Number((*index - 1) as f64), v))))
+                }))
+            }
+            Chain(first, second) => {
+                match next_value(first, span, slash)? {
+                    Some(v) => Ok(Some(v)),
+                    None => next_value(second, span, slash)
+                }
+            }
+        }
+    }
+}
+
+pub fn next_value(iter: &Iter, span: &Span, slash: &Slash) -> Result<Option<Value>, SlashError> {
+    let mut iter = iter.try_borrow_mut().map_err(|_| SlashError::new(span, "The iterator is already running"))?;
+    iter.next(span, slash)
+}
+
+// Lists are iterated as they are when the iteration starts, strings by character, tables by key
+// and process results by the lines of stdout
+pub fn to_iterator(value: &Value, span: &Span) -> Result<Iter, SlashError> {
+    let iterator = match value {
+        Value::Iterator(iter) => return Ok(iter.clone()),
+        Value::List(l) => ValueIterator::Values(l.borrow().clone(), 0),
+        Value::String(s) => ValueIterator::Values(s.chars().map(|c| Value::String(c.to_string())).collect(), 0),
+        Value::Table(t) => ValueIterator::Values(t.borrow().keys().map(|k| Value::String(k.clone())).collect(), 0),
+        Value::ProcessResult(_, stdout, _) => ValueIterator::Values(stdout.lines().map(|l| Value::String(l.to_owned())).collect(), 0),
+        _ => return Err(SlashError::new(span, &format!("Expected a List, Table, String, ProcessResult or Iterator to iterate over, but got a {}", value.value_type())))
+    };
+    Ok(Rc::new(RefCell::new(iterator)))
+}
+
+// The items of a for-in loop. Each item has a key, which is the index of the item, or the key
+// of the field when iterating a table with two loop variables
+pub struct ForInIterator {
+    iter: Iter,
+    index: usize,
+    table_fields: bool,
+}
+
+impl ForInIterator {
+    pub fn new(iterable: Iterable, key_value: bool, span: &Span) -> Result<ForInIterator, SlashError> {
+        let (iter, table_fields) = match iterable {
+            Iterable::Range(from, to) => (Rc::new(RefCell::new(ValueIterator::Range(from, to))), false),
+            Iterable::Value(Value::Table(t)) if key_value => {
+                let fields = t.borrow().iter()
+                    .map(|(k, v)| Value::List(Rc::new(Container::new(vec!(Value::String(k.clone()), v.clone())))))
+                    .collect();
+                (Rc::new(RefCell::new(ValueIterator::Values(fields, 0))), true)
+            }
+            Iterable::Value(v) => (to_iterator(&v, span)?, false)
+        };
+        Ok(ForInIterator { iter, index: 0, table_fields })
+    }
+
+    pub fn next(&mut self, span: &Span, slash: &Slash) -> Result<Option<(Value, Value)>, SlashError> {
+        match next_value(&self.iter, span, slash)? {
+            None => Ok(None),
+            Some(Value::List(field)) if self.table_fields => {
+                let field = field.borrow();
+                Ok(Some((field[0].clone(), field[1].clone())))
+            }
+            Some(value) => {
+                self.index += 1;
+                Ok(Some((Value::Number((self.index - 1) as f64), value)))
+            }
+        }
+    }
+}
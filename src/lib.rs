@@ -8,33 +8,76 @@ mod evaluate;
 mod value;
 mod function;
 mod error;
+mod pattern;
+mod iterator;
+mod generator;
+mod record;
+mod check;
 
 use pest::{Parser, Span};
 use pest::iterators::Pair;
 use duct;
 use std::ffi::OsString;
 use crate::closure::{Closure};
-use crate::evaluate::{evaluate_to_value, evaluate_env_var};
+use crate::evaluate::{evaluate_to_value, evaluate_to_iterable, evaluate_env_var, evaluate_string_literal, evaluate_raw_string_literal, evaluate_assignment, evaluate_slice_range};
 use std::io::Write;
-use crate::function::{function_call, Function, add_builtin_to_closure};
-use crate::value::Value;
+use crate::function::{function_call, Function, FunctionBody, add_builtin_to_closure, signature, annotated_type};
+use crate::iterator::ForInIterator;
+use crate::record::RecordType;
+use crate::value::{Value, Container};
 use std::rc::Rc;
 use crate::error::SlashError;
+use crate::pattern::{destructure, destructure_declaration, match_pattern, check_type};
 use std::cell::RefCell;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use indexmap::IndexMap;
 use std::path::PathBuf;
 use std::env;
 use std::fs::OpenOptions;
 use std::ops::Add;
+use regex::Regex;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 #[derive(Debug, Clone)]
 pub enum ExecuteResult<'a> {
     Return(Value, Span<'a>),
-    Break(Span<'a>),
-    Continue(Span<'a>),
+    Break(Span<'a>, Option<&'a str>),
+    Continue(Span<'a>, Option<&'a str>),
     None,
 }
 
+// What a loop does after its body has run. A break or continue without a label, or with the
+// label of the loop, is handled by the loop, anything else leaves the loop
+enum LoopControl<'a> {
+    Next,
+    Exit,
+    Leave(ExecuteResult<'a>),
+}
+
+impl<'a> ExecuteResult<'a> {
+    fn loop_control(self, label: Option<&str>) -> LoopControl<'a> {
+        match self {
+            ExecuteResult::None | ExecuteResult::Continue(_, None) => LoopControl::Next,
+            ExecuteResult::Break(_, None) => LoopControl::Exit,
+            ExecuteResult::Continue(_, Some(l)) if Some(l) == label => LoopControl::Next,
+            ExecuteResult::Break(_, Some(l)) if Some(l) == label => LoopControl::Exit,
+            result => LoopControl::Leave(result)
+        }
+    }
+
+    // The error for a break or continue that is not handled by any enclosing loop
+    pub(crate) fn unexpected_loop_control(&self) -> SlashError {
+        match self {
+            ExecuteResult::Break(s, None) => SlashError::new(s, "Unexpected break"),
+            ExecuteResult::Continue(s, None) => SlashError::new(s, "Unexpected continue"),
+            ExecuteResult::Break(s, Some(label)) | ExecuteResult::Continue(s, Some(label)) =>
+                SlashError::new(s, &format!("No enclosing loop has the label {}", label)),
+            _ => unreachable!()
+        }
+    }
+}
+
 impl ExecuteResult<'_> {
     fn is_none(&self) -> bool {
         if let ExecuteResult::None = self { true } else { false }
@@ -51,23 +94,36 @@ pub struct Slash<'a> {
     stderr: Box<RefCell<dyn Write>>,
     include_dir: RefCell<PathBuf>,
     args: Rc<Vec<String>>,
+    // Compiled regular expression literals of the program by their source, so a literal in a loop is only
+    // compiled once. Strings compiled at runtime are not kept here
+    regexes: RefCell<HashMap<String, Rc<Regex>>>,
+    // The random number generator of random and random_int, it can be seeded with random_seed
+    pub(crate) rng: RefCell<StdRng>,
 }
 
 impl Slash<'_> {
     pub fn new<'a>(source: &'a str, stdout: Box<RefCell<dyn Write>>,
                    stderr: Box<RefCell<dyn Write>>, include_dir: PathBuf,
                    args: Vec<String>) -> Slash<'a> {
-        Slash { source, stdout, stderr, include_dir: RefCell::new(include_dir), args: Rc::new(args) }
+        Slash { source, stdout, stderr, include_dir: RefCell::new(include_dir), args: Rc::new(args),
+                regexes: RefCell::new(HashMap::new()), rng: RefCell::new(StdRng::from_entropy()) }
     }
 
     pub fn run(&self) -> Result<(), SlashError> {
-        let mut root = Closure::new();
+        let mut builtins = Closure::new();
         let mut pairs = SlashParser::parse(Rule::file, self.source)?;
-        add_builtin_to_closure(&mut root);
+        add_builtin_to_closure(&mut builtins);
+        // The script runs in a scope of its own, so its declarations shadow the builtin functions
+        let mut root = builtins.derived();
         self.execute(pairs.next().unwrap(), &mut root)?;
         Ok(())
     }
 
+    // Type checks the script and the files it includes without running it, and returns all the errors found
+    pub fn check(&self) -> Vec<SlashError> {
+        check::check(self.source, self.include_dir.borrow().clone())
+    }
+
     fn execute<'a>(&self, pair: Pair<'a, Rule>, closure: &mut Closure) -> Result<ExecuteResult<'a>, SlashError> {
         match pair.as_rule() {
             Rule::file => {
@@ -75,9 +131,8 @@ impl Slash<'_> {
                     let res = self.execute(p, closure)?;
                     match res {
                         ExecuteResult::None => {}
-                        ExecuteResult::Continue(s) => return Err(SlashError::new(&s, "Unexpected continue")),
-                        ExecuteResult::Break(s) => return Err(SlashError::new(&s, "Unexpected break")),
-                        ExecuteResult::Return(_, s) => return Err(SlashError::new(&s, "Unexpected return"))
+                        ExecuteResult::Return(_, s) => return Err(SlashError::new(&s, "Unexpected return")),
+                        res => return Err(res.unexpected_loop_control())
                     }
                 }
             }
@@ -90,81 +145,96 @@ impl Slash<'_> {
             }
             Rule::function_call_statement => { function_call(pair, closure, self)?; }
             Rule::var_declaration => {
-                let mut pairs = pair.into_inner();
-                let var_name = pairs.next().unwrap().as_str();
+                let mut pairs = pair.into_inner().peekable();
+                let constant = pairs.next_if(|p| p.as_rule() == Rule::constant).is_some();
+                let pattern = pairs.next().unwrap();
+                let annotation = pairs.next_if(|p| p.as_rule() == Rule::type_annotation);
                 let expression = pairs.next().unwrap();
                 let value = evaluate_to_value(expression, closure, self)?;
-                closure.declare(var_name, value);
+                if let Some(annotation) = annotation {
+                    let span = annotation.as_span();
+                    check_type(&value, &annotated_type(annotation), pattern.as_str(), &span)?;
+                }
+                destructure_declaration(pattern, value, constant, closure, self)?;
             }
             Rule::var_assignment => {
                 let mut pairs = pair.into_inner();
                 let var_pair = pairs.next().unwrap();
                 let var_name = var_pair.as_str().trim();
                 if closure.has_var(var_name) {
+                    let operator = pairs.next().unwrap();
                     let expression = pairs.next().unwrap();
                     let value = evaluate_to_value(expression, closure, self)?;
-                    closure.assign(var_name, value);
+                    let value = evaluate_assignment(&operator, || Ok(closure.lookup(var_name)), value)?;
+                    closure.assign(var_name, value).map_err(|e| SlashError::new(&var_pair.as_span(), &e))?;
                 } else {
                     return Err(SlashError::new(&var_pair.as_span(), &format!("Variable {} not defined.", var_name)));
                 }
             }
-            Rule::indexed_var_assignment => {
-                let mut pairs = pair.into_inner();
+            Rule::path_var_assignment => {
+                let mut pairs = pair.into_inner().peekable();
+                let auto_create = pairs.next_if(|p| p.as_rule() == Rule::auto_create).is_some();
                 let var_pair = pairs.next().unwrap();
-                let var_span = var_pair.as_span();
                 let var_name = var_pair.as_str().trim();
+                if !closure.has_var(var_name) {
+                    return Err(SlashError::new(&var_pair.as_span(), &format!("Variable {} not defined.", var_name)));
+                }
 
-                if closure.has_var(var_name) {
-                    let expr_pair = pairs.next().unwrap();
-                    let expr_span = expr_pair.as_span();
-                    let index = evaluate_to_value(expr_pair, closure, &self)?;
-                    let expression = pairs.next().unwrap();
-                    let value = evaluate_to_value(expression, closure, self)?;
-
-                    let lhs_val = closure.lookup(var_name);
-                    let lhs_val_type = lhs_val.value_type();
-                    match &lhs_val {
-                        Value::List(l) => {
-                            let i_index;
-                            if let Value::Number(raw) = index {
-                                if 0.0 <= raw && raw < l.borrow().len() as f64 {
-                                    i_index = usize::from(raw as u16);
-                                    l.borrow_mut()[i_index] = value;
-                                } else {
-                                    return Err(SlashError::new(&expr_span, &format!("Index out of bounds. Value length is {} index was {}", l.borrow().len(), raw)));
-                                }
-                            } else {
-                                return Err(SlashError::new(&expr_span, &format!("Index value not a number, but a {}", lhs_val_type)));
-                            }
+                let mut accessors: Vec<_> = pairs.collect();
+                let expression = accessors.pop().unwrap();
+                let operator = accessors.pop().unwrap();
+                let last_accessor = accessors.pop().unwrap();
+
+                let mut container = closure.lookup(var_name);
+                // The container and index the current container was looked up from, assigning into a string
+                // gives a new string that is assigned back there
+                let mut parent = None;
+                for accessor in accessors {
+                    let span = accessor.as_span();
+                    let index = self.evaluate_accessor(accessor, closure)?;
+                    let next = match (&container, &index) {
+                        (Value::Table(t), Value::String(key)) if auto_create && !t.borrow().contains_key(key) => {
+                            let created = Value::Table(Rc::new(Container::new(IndexMap::new())));
+                            container.assign_by_index(&index, created.clone(), &span)?;
+                            created
                         }
-                        Value::Table(t) => {
-                            if let Value::String(s) = index {
-                                t.borrow_mut().insert(s, value);
-                            } else {
-                                return Err(SlashError::new(&expr_span, &format!("Index value not a string, but a {}", lhs_val_type)));
-                            }
-                        }
-                        _ => return Err(SlashError::new(&var_span, &format!("Left hand side variables value is not table or list, but {}", lhs_val.value_type())))
-                    }
-                } else {
-                    return Err(SlashError::new(&var_pair.as_span(), &format!("Variable {} not defined.", var_name)));
+                        _ => container.lookup_by_index(&index, &span)?
+                    };
+                    parent = Some((container, index));
+                    container = next;
                 }
-            }
-            Rule::dot_var_assignment => {
-                let mut pairs = pair.into_inner();
-                let table_identifier = pairs.next().unwrap();
-                let field_identifier = pairs.next().unwrap();
-                let expression = pairs.next().unwrap();
-                if closure.has_var(table_identifier.as_str()) {
-                    let table_value = closure.lookup(table_identifier.as_str());
-                    if let Value::Table(table) = table_value {
-                        let value = evaluate_to_value(expression, closure, self)?;
-                        table.borrow_mut().insert(field_identifier.as_str().to_owned(), value);
-                    } else {
-                        return Err(SlashError::new(&table_identifier.as_span(), &format!("Variable {} is not a table.", table_identifier.as_str())));
+
+                let span = last_accessor.as_span();
+                container.check_not_frozen(&span)?;
+                if last_accessor.as_rule() == Rule::push_accessor {
+                    if operator.as_rule() != Rule::assign {
+                        return Err(SlashError::new(&operator.as_span(), "Only plain assignment is allowed when pushing to a list"));
+                    }
+                    let value = evaluate_to_value(expression, closure, self)?;
+                    container.push(value, &span)?;
+                } else if let Value::String(_) = container {
+                    let (from, to) = match self.slice_accessor(last_accessor.clone(), closure)? {
+                        Some(range) => range,
+                        None => container.string_index_range(&self.evaluate_accessor(last_accessor, closure)?, &span)?
+                    };
+                    let value = evaluate_to_value(expression, closure, self)?;
+                    let value = evaluate_assignment(&operator, || container.slice(&from, &to, &span), value)?;
+                    let replaced = container.replace_string_slice(&from, &to, &value, &span)?;
+                    match parent {
+                        Some((parent, index)) => parent.assign_by_index(&index, replaced, &span)?,
+                        None => closure.assign(var_name, replaced).map_err(|e| SlashError::new(&var_pair.as_span(), &e))?
+                    }
+                } else if let Some((from, to)) = self.slice_accessor(last_accessor.clone(), closure)? {
+                    if operator.as_rule() != Rule::assign {
+                        return Err(SlashError::new(&operator.as_span(), "Only plain assignment is allowed when assigning to a slice"));
                     }
+                    let value = evaluate_to_value(expression, closure, self)?;
+                    container.assign_slice(&from, &to, value, &span)?;
                 } else {
-                    return Err(SlashError::new(&table_identifier.as_span(), &format!("Variable {} not defined.", table_identifier.as_str())));
+                    let index = self.evaluate_accessor(last_accessor, closure)?;
+                    let value = evaluate_to_value(expression, closure, self)?;
+                    let value = evaluate_assignment(&operator, || container.lookup_by_index(&index, &span), value)?;
+                    container.assign_by_index(&index, value, &span)?;
                 }
             }
             Rule::chain => {
@@ -192,74 +262,13 @@ impl Slash<'_> {
 
                 self.run_chain(command, vec, redirection, append, capture, closure)?;
             }
-            Rule::while_statement => {
-                let mut pairs = pair.into_inner();
-                let expression = pairs.next().unwrap();
-                let body = pairs.next().unwrap();
-                let mut inner_closure = closure.derived();
-                loop {
-                    if !evaluate_to_value(expression.clone(), closure, self)?.is_true() {
-                        break;
-                    }
-
-                    match self.execute_loop_body(body.clone(), &mut inner_closure)? {
-                        ExecuteResult::Return(v,s) => return Ok(ExecuteResult::Return(v,s)),
-                        ExecuteResult::Break(_) => { break; }
-                        _ => {}
-                    }
-                }
-            }
-            Rule::for_in_statement => {
-                let mut pairs = pair.into_inner();
-                let var_name = pairs.next().unwrap().as_str();
-                let expression = pairs.next().unwrap();
-                let expression_span = expression.as_span();
-                if let Value::List(list) = evaluate_to_value(expression, closure, self)? {
-                    let block = pairs.next().unwrap();
-                    let mut inner_closure = closure.derived();
-
-                    for v in list.borrow().iter() {
-                        inner_closure.declare(var_name, v.clone());
-                        match self.execute_loop_body(block.clone(), &mut inner_closure)? {
-                            ExecuteResult::Return(v,s) => return Ok(ExecuteResult::Return(v,s)),
-                            ExecuteResult::Break(_) => { break; }
-                            _ => {}
-                        }
-                    }
-                } else {
-                    return Err(SlashError::new(&expression_span, &format!("Expected list value")));
-                }
+            Rule::while_statement | Rule::do_while_statement | Rule::for_in_statement | Rule::for_std_statement => {
+                return self.execute_loop(pair, None, closure);
             }
-            Rule::for_std_statement => {
+            Rule::labeled_loop => {
                 let mut pairs = pair.into_inner();
-                let var_name = pairs.next().unwrap().as_str();
-                let init_expression = pairs.next().unwrap();
-                let continue_expression = pairs.next().unwrap();
-                let update_assignment = pairs.next().unwrap();
-                let update_assignment_span = update_assignment.as_span();
-                let mut update_assignment_pairs = update_assignment.into_inner();
-                let update_var_name = update_assignment_pairs.next().unwrap();
-                if var_name != update_var_name.as_str() {
-                    return Err(SlashError::new(&update_assignment_span, &format!("Expected update term to update loop variable {}, but it updated variable {}", var_name, update_var_name.as_str())));
-                }
-                let update_expression = update_assignment_pairs.next().unwrap();
-                let block = pairs.next().unwrap();
-                let mut inner_closure = closure.derived();
-                let loop_value = evaluate_to_value(init_expression, &mut inner_closure, self)?;
-                inner_closure.declare(var_name, loop_value);
-                loop {
-                    let val = evaluate_to_value(continue_expression.clone(), &mut inner_closure, self)?;
-                    if !val.is_true() { break; }
-
-                    match self.execute_loop_body(block.clone(), &mut inner_closure)? {
-                        ExecuteResult::Return(v,s) => return Ok(ExecuteResult::Return(v,s)),
-                        ExecuteResult::Break(_) => { break; }
-                        _ => {}
-                    }
-
-                    let loop_value = evaluate_to_value(update_expression.clone(), &mut inner_closure, self)?;
-                    inner_closure.assign(var_name, loop_value);
-                }
+                let label = pairs.next().unwrap().as_str();
+                return self.execute_loop(pairs.next().unwrap(), Some(label), closure);
             }
             Rule::if_statement => {
                 let mut pairs = pair.into_inner();
@@ -287,24 +296,31 @@ impl Slash<'_> {
             }
             Rule::function_declaration => {
                 let mut pairs = pair.into_inner();
-                let function_name = pairs.next().unwrap().as_str();
-                let mut formal_args = Vec::new();
-                loop {
-                    let p = pairs.next().unwrap();
-                    match p.as_rule() {
-                        Rule::var_name => formal_args.push(String::from(p.as_str())),
-                        Rule::block => {
-                            closure.declare(function_name,
-                                            Value::Function(Function::User(
-                                                Rc::new(formal_args),
-                                                String::from(p.as_str()),
-                                                closure.clone(),
-                                            )));
-                            break;
-                        }
-                        _ => unreachable!()
+                let function_pair = pairs.next().unwrap();
+                let function_name = function_pair.as_str();
+                let mut children: Vec<_> = pairs.collect();
+                let body = children.pop().unwrap();
+                let signature = signature(children.into_iter())?;
+                closure.declare(function_name,
+                                Value::Function(Function::User(
+                                    Rc::new(signature),
+                                    FunctionBody::from_block(&body),
+                                    closure.clone(),
+                                ))).map_err(|e| SlashError::new(&function_pair.as_span(), &e))?;
+            }
+            Rule::struct_declaration => {
+                let mut pairs = pair.into_inner();
+                let name_pair = pairs.next().unwrap();
+                let name = name_pair.as_str();
+                let mut fields: Vec<String> = Vec::new();
+                for field in pairs {
+                    if fields.iter().any(|f| f == field.as_str()) {
+                        return Err(SlashError::new(&field.as_span(), &format!("Duplicate field {}", field.as_str())));
                     }
+                    fields.push(field.as_str().to_owned());
                 }
+                closure.declare(name, Value::Function(Function::Constructor(Rc::new(RecordType { name: name.to_owned(), fields }))))
+                    .map_err(|e| SlashError::new(&name_pair.as_span(), &e))?;
             }
             Rule::return_statement => {
                 let span = pair.as_span();
@@ -312,15 +328,24 @@ impl Slash<'_> {
                 let value = evaluate_to_value(expression, closure, self)?;
                 return Ok(ExecuteResult::Return(value, span));
             }
-            Rule::break_statement => { return Ok(ExecuteResult::Break(pair.as_span())); }
-            Rule::continue_statement => { return Ok(ExecuteResult::Continue(pair.as_span())); }
+            Rule::yield_statement => {
+                return Err(SlashError::new(&pair.as_span(), "yield can only be used in the body of a function"));
+            }
+            Rule::break_statement => {
+                let span = pair.as_span();
+                return Ok(ExecuteResult::Break(span, pair.into_inner().next().map(|l| l.as_str())));
+            }
+            Rule::continue_statement => {
+                let span = pair.as_span();
+                return Ok(ExecuteResult::Continue(span, pair.into_inner().next().map(|l| l.as_str())));
+            }
             Rule::export_statement => {
                 let mut pairs = pair.into_inner();
                 let var_pair = pairs.next().unwrap();
                 let var_name = var_pair.as_str().trim();
                 if let Some(expr_pair) = pairs.next() {
                     let value = evaluate_to_value(expr_pair, closure, self)?;
-                    closure.declare(var_name, value);
+                    closure.declare(var_name, value).map_err(|e| SlashError::new(&var_pair.as_span(), &e))?;
                 } else {
                     if !closure.has_var(var_name) {
                         return Err(SlashError::new(&var_pair.as_span(), &format!("Exported variable {} not defined.", var_name)));
@@ -331,16 +356,12 @@ impl Slash<'_> {
             Rule::match_statement => {
                 let mut pairs = pair.into_inner();
                 let match_value = evaluate_to_value(pairs.next().unwrap(), closure, self)?;
-                loop {
-                    match pairs.next() {
-                        None => break,
-                        Some(match_term) => {
-                            let mut mt: Vec<_> = match_term.into_inner().collect();
-                            let block = mt.remove(mt.len() - 1);
-                            if self.matches(&match_value, mt, closure)? {
-                                self.execute(block, closure)?;
-                            }
-                        }
+                for match_arm in pairs {
+                    let mut patterns: Vec<_> = match_arm.into_inner().collect();
+                    let block = patterns.pop().unwrap();
+                    let guard = if matches!(patterns.last(), Some(p) if p.as_rule() == Rule::match_guard) { patterns.pop() } else { None };
+                    if let Some(mut arm_closure) = self.matches(&match_value, patterns, guard, closure)? {
+                        return self.execute(block, &mut arm_closure);
                     }
                 }
             }
@@ -350,14 +371,147 @@ impl Slash<'_> {
         Ok(ExecuteResult::None)
     }
 
-    fn execute_loop_body<'a>(&self, block: Pair<'a,Rule>, mut closure: &mut Closure) -> Result<ExecuteResult<'a>, SlashError>{
+    fn evaluate_accessor(&self, accessor: Pair<Rule>, closure: &mut Closure) -> Result<Value, SlashError> {
+        let inner = accessor.into_inner().next().unwrap();
+        match inner.as_rule() {
+            Rule::var_name => Ok(Value::String(inner.as_str().to_owned())),
+            Rule::slice_range => Err(SlashError::new(&inner.as_span(), "A slice can only be the last accessor of an assignment")),
+            _ => evaluate_to_value(inner, closure, self)
+        }
+    }
+
+    // The bounds of an index accessor that is a slice, as in l[from..to] = value
+    fn slice_accessor(&self, accessor: Pair<Rule>, closure: &mut Closure) -> Result<Option<(Value, Value)>, SlashError> {
+        match accessor.into_inner().next() {
+            Some(inner) if inner.as_rule() == Rule::slice_range => Ok(Some(evaluate_slice_range(inner, closure, self)?)),
+            _ => Ok(None)
+        }
+    }
+
+    // Applies the update assignment of a for loop, which must assign to the loop variable
+    fn update_loop_variable(&self, var_name: &str, update_assignment: Pair<Rule>, closure: &mut Closure) -> Result<(), SlashError> {
+        let update_assignment_span = update_assignment.as_span();
+        let mut pairs = update_assignment.into_inner();
+        let update_var_name = pairs.next().unwrap();
+        if var_name != update_var_name.as_str() {
+            return Err(SlashError::new(&update_assignment_span, &format!("Expected update term to update loop variable {}, but it updated variable {}", var_name, update_var_name.as_str())));
+        }
+        let update_operator = pairs.next().unwrap();
+        let value = evaluate_to_value(pairs.next().unwrap(), closure, self)?;
+        let value = evaluate_assignment(&update_operator, || Ok(closure.lookup(var_name)), value)?;
+        closure.assign(var_name, value).map_err(|e| SlashError::new(&update_assignment_span, &e))
+    }
+
+    // Runs a while, do-while, for-in or for loop. The label is given when the loop is labeled
+    fn execute_loop<'a>(&self, pair: Pair<'a, Rule>, label: Option<&str>, closure: &mut Closure) -> Result<ExecuteResult<'a>, SlashError> {
+        match pair.as_rule() {
+            Rule::while_statement => {
+                let mut pairs = pair.into_inner();
+                let expression = pairs.next().unwrap();
+                let body = pairs.next().unwrap();
+                let mut inner_closure = closure.derived();
+                loop {
+                    if !evaluate_to_value(expression.clone(), closure, self)?.is_true() {
+                        break;
+                    }
+
+                    match self.execute_loop_body(body.clone(), &mut inner_closure)?.loop_control(label) {
+                        LoopControl::Next => {}
+                        LoopControl::Exit => break,
+                        LoopControl::Leave(res) => return Ok(res)
+                    }
+                }
+            }
+            Rule::do_while_statement => {
+                let mut pairs = pair.into_inner();
+                let body = pairs.next().unwrap();
+                let expression = pairs.next().unwrap();
+                let mut inner_closure = closure.derived();
+                loop {
+                    match self.execute_loop_body(body.clone(), &mut inner_closure)?.loop_control(label) {
+                        LoopControl::Next => {}
+                        LoopControl::Exit => break,
+                        LoopControl::Leave(res) => return Ok(res)
+                    }
+
+                    if !evaluate_to_value(expression.clone(), closure, self)?.is_true() {
+                        break;
+                    }
+                }
+            }
+            Rule::for_in_statement => {
+                let mut pairs: Vec<_> = pair.into_inner().collect();
+                let block = pairs.pop().unwrap();
+                let expression = pairs.pop().unwrap();
+                let expression_span = expression.as_span();
+                let value_pattern = pairs.pop().unwrap();
+                let key_pattern = pairs.pop();
+
+                let mut items = ForInIterator::new(evaluate_to_iterable(expression, closure, self)?, key_pattern.is_some(), &expression_span)?;
+                let mut inner_closure = closure.derived();
+                while let Some((key, value)) = items.next(&expression_span, self)? {
+                    if let Some(key_pattern) = &key_pattern {
+                        destructure(key_pattern.clone(), key, &mut inner_closure, self)?;
+                    }
+                    destructure(value_pattern.clone(), value, &mut inner_closure, self)?;
+                    match self.execute_loop_body(block.clone(), &mut inner_closure)?.loop_control(label) {
+                        LoopControl::Next => {}
+                        LoopControl::Exit => break,
+                        LoopControl::Leave(res) => return Ok(res)
+                    }
+                }
+            }
+            Rule::for_std_statement => {
+                let mut pairs = pair.into_inner();
+                let var_pair = pairs.next().unwrap();
+                let var_name = var_pair.as_str();
+                let init_expression = pairs.next().unwrap();
+                let continue_expression = pairs.next().unwrap();
+                let update_assignment = pairs.next().unwrap();
+                let block = pairs.next().unwrap();
+                let mut inner_closure = closure.derived();
+                let loop_value = evaluate_to_value(init_expression, &mut inner_closure, self)?;
+                inner_closure.declare(var_name, loop_value).map_err(|e| SlashError::new(&var_pair.as_span(), &e))?;
+                loop {
+                    let val = evaluate_to_value(continue_expression.clone(), &mut inner_closure, self)?;
+                    if !val.is_true() { break; }
+
+                    match self.execute_loop_body(block.clone(), &mut inner_closure)?.loop_control(label) {
+                        LoopControl::Next => {}
+                        LoopControl::Exit => break,
+                        LoopControl::Leave(res) => return Ok(res)
+                    }
+
+                    self.update_loop_variable(var_name, update_assignment.clone(), &mut inner_closure)?;
+                }
+            }
+            _ => unreachable!("{:?}", pair.as_rule())
+        }
+        Ok(ExecuteResult::None)
+    }
+
+    // A regular expression literal of the program, compiled the first time it is evaluated
+    pub(crate) fn regex_literal(&self, source: &str, span: &Span) -> Result<Rc<Regex>, SlashError> {
+        if let Some(regex) = self.regexes.borrow().get(source) {
+            return Ok(regex.clone());
+        }
+        let regex = Slash::compile_regex(source, span)?;
+        self.regexes.borrow_mut().insert(source.to_owned(), regex.clone());
+        Ok(regex)
+    }
+
+    pub(crate) fn compile_regex(source: &str, span: &Span) -> Result<Rc<Regex>, SlashError> {
+        Regex::new(source).map(Rc::new).map_err(|e| SlashError::new(span, &format!("Invalid regular expression: {}", e)))
+    }
+
+    // Each iteration runs in a scope of its own, so the declarations of one iteration do not clash with the next
+    fn execute_loop_body<'a>(&self, block: Pair<'a,Rule>, closure: &mut Closure) -> Result<ExecuteResult<'a>, SlashError>{
+        let mut iteration_closure = closure.derived();
         for p in block.into_inner() {
-            let res = self.execute(p, &mut closure)?;
+            let res = self.execute(p, &mut iteration_closure)?;
             match res {
-                ExecuteResult::Break(s) => return Ok(ExecuteResult::Break(s)),
-                ExecuteResult::Continue(_) => break,
-                ExecuteResult::Return(v, s) => return Ok(ExecuteResult::Return(v, s)),
-                ExecuteResult::None => ()
+                ExecuteResult::None => (),
+                res => return Ok(res)
             }
         }
         Ok(ExecuteResult::None)
@@ -391,8 +545,9 @@ impl Slash<'_> {
         let out = cmd.unchecked().run().or_else(|e| { Err(SlashError::new(&command_span, &e.to_string())) })?;
 
         if let Some(pair) = capture {
-            let var_name = pair.into_inner().next().unwrap().as_str();
-            closure.declare(var_name, Value::ProcessResult(out.status.code(), String::from_utf8(out.stdout).unwrap(), String::from_utf8(out.stderr).unwrap()));
+            let var_pair = pair.into_inner().next().unwrap();
+            let value = Value::ProcessResult(out.status.code(), String::from_utf8(out.stdout).unwrap(), String::from_utf8(out.stderr).unwrap());
+            closure.declare(var_pair.as_str(), value).map_err(|e| SlashError::new(&var_pair.as_span(), &e))?;
         } else {
             self.stdout.borrow_mut().write(&out.stdout).expect("Failed to write to stdout");
             self.stderr.borrow_mut().write(&out.stderr).expect("Failed to write to stderr");
@@ -456,7 +611,8 @@ impl Slash<'_> {
     fn parse_prg_or_arg(&self, term: Pair<Rule>, closure: &mut Closure) -> Result<String, SlashError> {
         match term.as_rule() {
             Rule::word => Ok(Self::unescape_prg_or_arg(&term)),
-            Rule::string_literal => Ok(Value::convert_parsed_string(term.as_str())),
+            Rule::string_literal => evaluate_string_literal(term, closure, self),
+            Rule::raw_string_literal => Ok(evaluate_raw_string_literal(term)),
             _ => {
                 let t_str = term.as_str();
                 let t_sp = term.as_span();
@@ -492,33 +648,26 @@ impl Slash<'_> {
         s
     }
 
-    fn matches(&self, value: &Value, match_expressions: Vec<Pair<Rule>>, closure: &mut Closure) -> Result<bool, SlashError> {
-        if match_expressions.is_empty() {
-            // If there are no match expressions, it is the catch_all rule "_ => {}"
-            return Ok(true);
-        }
+    // Returns a closure with the bindings of the first pattern that matches the value and satisfies the guard
+    fn matches(&self, value: &Value, patterns: Vec<Pair<Rule>>, guard: Option<Pair<Rule>>, closure: &Closure) -> Result<Option<Closure>, SlashError> {
+        for pattern in patterns {
+            let mut arm_closure = closure.derived();
+            if !match_pattern(pattern, value, &mut arm_closure, self)? {
+                continue;
+            }
 
-        for match_expression_pair in match_expressions {
-            let mut expressions = match_expression_pair.into_inner();
-            let first_expression_pair = expressions.next().unwrap();
-            let first_expression_pair_span = first_expression_pair.as_span();
-            let first = evaluate_to_value(first_expression_pair, closure, self)?;
-
-            if let Some(second) = expressions.next() {
-                let second_expression_pair_span = second.as_span();
-                let second = evaluate_to_value(second, closure, self)?;
-                if first._less_than_or_equals(value, &first_expression_pair_span)? &&
-                    second._greater_than_or_equals(value, &second_expression_pair_span)? {
-                    return Ok(true);
-                }
-            } else {
-                if value._equals(&first, &first_expression_pair_span)? {
-                    return Ok(true);
+            match &guard {
+                Some(guard) => {
+                    let expression = guard.clone().into_inner().next().unwrap();
+                    if evaluate_to_value(expression, &mut arm_closure, self)?.is_true() {
+                        return Ok(Some(arm_closure));
+                    }
                 }
+                None => return Ok(Some(arm_closure))
             }
         }
 
-        return Ok(false);
+        Ok(None)
     }
 }
 
@@ -11,6 +11,9 @@ fn main() {
 
     /* let program = */ args.remove(0);
 
+    let check = args.first().map(|a| a == "check").unwrap_or(false);
+    if check { args.remove(0); }
+
     if args.len() == 0 {
         stdin().read_to_string(&mut src).expect("Could not read from stdin");
         cur_dir = env::current_dir().expect("Could not determine current dir");
@@ -23,12 +26,22 @@ fn main() {
         panic!("Could not parse command line args: {:?}",&args);
     }
 
-    let res = slash::Slash::new(&src,
+    let slash = slash::Slash::new(&src,
                                 Box::new(RefCell::new(stdout())),
                                 Box::new(RefCell::new(stderr())),
                                 cur_dir,
                                  args
-    ).run();
+    );
+
+    if check {
+        let errors = slash.check();
+        for err in &errors {
+            eprintln!("{}", err);
+        }
+        exit(if errors.is_empty() { 0 } else { 1 });
+    }
+
+    let res = slash.run();
 
     match res {
         Ok(()) => exit(0),
@@ -0,0 +1,222 @@
+// Destructuring and matching of values against patterns, used by let declarations, for loops and match statements
+
+use pest::iterators::Pair;
+use pest::Span;
+use crate::{Rule, Slash};
+use crate::value::{Value, Container};
+use crate::closure::Closure;
+use crate::error::SlashError;
+use crate::evaluate::{evaluate_to_value, evaluate_string_literal, evaluate_raw_string_literal};
+use std::rc::Rc;
+
+// The outcome of matching a value against a pattern, a failed match holds an error describing the mismatch
+enum Matched {
+    Yes,
+    No(SlashError),
+}
+
+type Bindings = Vec<(String, Value)>;
+
+pub fn destructure(pattern: Pair<Rule>, value: Value, closure: &mut Closure, slash: &Slash) -> Result<(), SlashError> {
+    let span = pattern.as_span();
+    let mut bindings = Vec::new();
+    match bind(pattern, value, &mut bindings, closure, slash)? {
+        Matched::Yes => declare(bindings, false, closure, &span),
+        Matched::No(err) => Err(err)
+    }
+}
+
+// Destructures the value of a let or const declaration
+pub fn destructure_declaration(pattern: Pair<Rule>, value: Value, constant: bool, closure: &mut Closure, slash: &Slash) -> Result<(), SlashError> {
+    let span = pattern.as_span();
+    let mut bindings = Vec::new();
+    match bind(pattern, value, &mut bindings, closure, slash)? {
+        Matched::Yes => declare(bindings, constant, closure, &span),
+        Matched::No(err) => Err(err)
+    }
+}
+
+// Matches a value against a match_pattern, declaring the bindings of the pattern in the closure if it matches
+pub fn match_pattern(pattern: Pair<Rule>, value: &Value, closure: &mut Closure, slash: &Slash) -> Result<bool, SlashError> {
+    let mut pairs = pattern.into_inner();
+    let first = pairs.next().unwrap();
+    let matched = match first.as_rule() {
+        Rule::expression => {
+            let from_span = first.as_span();
+            let from = evaluate_to_value(first, closure, slash)?;
+            if let Some(to) = pairs.next() {
+                let to_span = to.as_span();
+                let to = evaluate_to_value(to, closure, slash)?;
+                same_type(value, &from) && same_type(value, &to) &&
+                    from._less_than_or_equals(value, &from_span)? && to._greater_than_or_equals(value, &to_span)?
+            } else {
+                same_type(value, &from) && value._equals(&from, &from_span)?
+            }
+        }
+        _ => {
+            let span = first.as_span();
+            let mut bindings = Vec::new();
+            match bind(first, value.clone(), &mut bindings, closure, slash)? {
+                Matched::Yes => {
+                    declare(bindings, false, closure, &span)?;
+                    true
+                }
+                Matched::No(_) => false
+            }
+        }
+    };
+    Ok(matched)
+}
+
+// Declares the bindings of a pattern. All the names are checked first, so either all or none of them are declared
+fn declare(bindings: Bindings, constant: bool, closure: &mut Closure, span: &Span) -> Result<(), SlashError> {
+    for (i, (var_name, _)) in bindings.iter().enumerate() {
+        closure.check_declaration(var_name).map_err(|e| SlashError::new(span, &e))?;
+        if constant && bindings[..i].iter().any(|(earlier, _)| earlier == var_name) {
+            return Err(SlashError::new(span, &format!("Constant {} is already declared in this scope", var_name)));
+        }
+    }
+    for (var_name, value) in bindings {
+        if constant {
+            closure.declare_constant(&var_name, value).map_err(|e| SlashError::new(span, &e))?;
+        } else {
+            closure.declare(&var_name, value).map_err(|e| SlashError::new(span, &e))?;
+        }
+    }
+    Ok(())
+}
+
+fn same_type(lhs: &Value, rhs: &Value) -> bool {
+    lhs.value_type() == rhs.value_type()
+}
+
+fn bind(pattern: Pair<Rule>, value: Value, bindings: &mut Bindings, closure: &mut Closure, slash: &Slash) -> Result<Matched, SlashError> {
+    let span = pattern.as_span();
+    match pattern.as_rule() {
+        Rule::var_name => bindings.push((pattern.as_str().to_owned(), value)),
+        Rule::wildcard_pattern => {}
+        Rule::list_pattern => {
+            let list = if let Value::List(list) = &value { list.borrow().clone() } else {
+                return Ok(Matched::No(SlashError::new(&span, &format!("Cannot destructure a {} with a list pattern", value.value_type()))));
+            };
+            let elements: Vec<_> = pattern.into_inner().collect();
+            let rest_positions: Vec<_> = elements.iter().enumerate().filter(|(_, e)| e.as_rule() == Rule::rest_pattern).map(|(i, _)| i).collect();
+            if rest_positions.len() > 1 {
+                return Err(SlashError::new(&elements[rest_positions[1]].as_span(), "A list pattern can only contain one rest pattern"));
+            }
+
+            if let Some(&rest_position) = rest_positions.first() {
+                let required = elements.len() - 1;
+                if list.len() < required {
+                    return Ok(Matched::No(SlashError::new(&span, &format!("List pattern expects at least {} elements, but the list has {}", required, list.len()))));
+                }
+                let suffix_start = list.len() - (elements.len() - rest_position - 1);
+                for (i, element) in elements.into_iter().enumerate() {
+                    let matched = if i < rest_position {
+                        bind(element, list[i].clone(), bindings, closure, slash)?
+                    } else if i == rest_position {
+                        if let Some(var_name) = element.into_inner().next() {
+                            let rest = list[rest_position..suffix_start].to_vec();
+                            bindings.push((var_name.as_str().to_owned(), Value::List(Rc::new(Container::new(rest)))));
+                        }
+                        Matched::Yes
+                    } else {
+                        bind(element, list[suffix_start + i - rest_position - 1].clone(), bindings, closure, slash)?
+                    };
+                    if let Matched::No(err) = matched { return Ok(Matched::No(err)); }
+                }
+            } else {
+                if list.len() > elements.len() {
+                    return Ok(Matched::No(SlashError::new(&span, &format!("List pattern expects {} elements, but the list has {}", elements.len(), list.len()))));
+                }
+                for (i, element) in elements.into_iter().enumerate() {
+                    let matched = match list.get(i) {
+                        Some(v) => bind(element, v.clone(), bindings, closure, slash)?,
+                        None => Matched::No(SlashError::new(&element.as_span(), &format!("List has no element at index {} for {}, it has {} elements", i, element.as_str(), list.len())))
+                    };
+                    if let Matched::No(err) = matched { return Ok(Matched::No(err)); }
+                }
+            }
+        }
+        Rule::table_pattern => {
+            let table = if let Value::Table(table) = &value { table.clone() } else {
+                return Ok(Matched::No(SlashError::new(&span, &format!("Cannot destructure a {} with a table pattern", value.value_type()))));
+            };
+            for field in pattern.into_inner() {
+                let field_span = field.as_span();
+                let mut pairs = field.into_inner();
+                let key_pair = pairs.next().unwrap();
+                let key = match key_pair.as_rule() {
+                    Rule::string_literal => evaluate_string_literal(key_pair.clone(), closure, slash)?,
+                    _ => key_pair.as_str().to_owned()
+                };
+                let field_value = table.borrow().get(&key).cloned();
+                let matched = match field_value {
+                    Some(v) => bind(pairs.next().unwrap_or(key_pair), v, bindings, closure, slash)?,
+                    None => Matched::No(SlashError::new(&field_span, &format!("Key {} not found in table", key)))
+                };
+                if let Matched::No(err) = matched { return Ok(Matched::No(err)); }
+            }
+        }
+        Rule::type_pattern => {
+            let mut pairs = pattern.into_inner();
+            let type_name = pairs.next().unwrap().as_str();
+            if !has_type(&value, type_name) {
+                return Ok(Matched::No(SlashError::new(&span, &format!("Expected a {} but the value is a {}", type_name, value.value_type()))));
+            }
+            if let Some(var_name) = pairs.next() {
+                bindings.push((var_name.as_str().to_owned(), value));
+            }
+        }
+        Rule::regex_literal => {
+            let regex_source = pattern.into_inner().next().unwrap().as_str();
+            let regex = slash.regex_literal(regex_source, &span)?;
+            let s = if let Value::String(s) = &value { s } else {
+                return Ok(Matched::No(SlashError::new(&span, &format!("Expected a String to match a regular expression, but the value is a {}", value.value_type()))));
+            };
+            match regex.captures(s) {
+                Some(captures) => {
+                    for name in regex.capture_names().flatten() {
+                        let captured = captures.name(name).map(|m| m.as_str()).unwrap_or("");
+                        bindings.push((name.to_owned(), Value::String(captured.to_owned())));
+                    }
+                }
+                None => return Ok(Matched::No(SlashError::new(&span, &format!("The value {} does not match the regular expression", s))))
+            }
+        }
+        Rule::literal_pattern => {
+            let literal = pattern.into_inner().next().unwrap();
+            let expected = match literal.as_rule() {
+                Rule::null_literal => Value::Null,
+                Rule::numeric_literal => Value::Number(literal.as_str().parse::<f64>().unwrap()),
+                Rule::string_literal => Value::String(evaluate_string_literal(literal, closure, slash)?),
+                Rule::raw_string_literal => Value::String(evaluate_raw_string_literal(literal)),
+                _ => unreachable!()
+            };
+            if !same_type(&value, &expected) || !value._equals(&expected, &span)? {
+                return Ok(Matched::No(SlashError::new(&span, &format!("Expected the value {} but found {}", expected.to_json(), value.to_json()))));
+            }
+        }
+        _ => unreachable!("Rule not handled {:?}", pattern.as_rule())
+    }
+    Ok(Matched::Yes)
+}
+
+fn has_type(value: &Value, type_name: &str) -> bool {
+    match (type_name, value) {
+        ("Any", _) | ("Number", Value::Number(_)) | ("String", Value::String(_)) | ("List", Value::List(_)) |
+        ("Table", Value::Table(_)) | ("Function", Value::Function(_)) | ("ProcessResult", Value::ProcessResult(..)) |
+        ("Iterator", Value::Iterator(_)) | ("Regex", Value::Regex(_)) | ("Null", Value::Null) => true,
+        (type_name, Value::Record(record_type, _)) => record_type.name == type_name,
+        _ => false
+    }
+}
+
+// Checks a value against a type annotation, what describes the annotated value in the error
+pub fn check_type(value: &Value, type_name: &str, what: &str, span: &Span) -> Result<(), SlashError> {
+    if has_type(value, type_name) {
+        Ok(())
+    } else {
+        Err(SlashError::new(span, &format!("Expected {} to be a {}, but it is a {}", what, type_name, value.value_type())))
+    }
+}
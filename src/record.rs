@@ -0,0 +1,60 @@
+// Records are the values of a type declared with a struct statement. A record has the fixed set of fields
+// of its type, and the name of the type is a constructor function for its records
+
+use crate::value::{Value, Container};
+use crate::function::NamedArg;
+use crate::error::SlashError;
+use indexmap::IndexMap;
+use pest::Span;
+use std::rc::Rc;
+
+#[derive(Debug)]
+pub struct RecordType {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+// The values of the fields of a record, in the order the fields are declared
+pub type Fields = Rc<Container<Vec<Value>>>;
+
+impl RecordType {
+    pub fn field_index(&self, field: &str, span: &Span) -> Result<usize, SlashError> {
+        self.fields.iter().position(|f| f == field)
+            .ok_or_else(|| SlashError::new(span, &format!("{} has no field named {}", self.name, field)))
+    }
+
+    // Constructs a record from the arguments of a call to the constructor, fields can be given by position or by name
+    pub fn construct(record_type: &Rc<RecordType>, args: Vec<Value>, spans: &[Span], named: Vec<NamedArg>) -> Result<Value, SlashError> {
+        let mut values: Vec<Option<Value>> = vec![None; record_type.fields.len()];
+        if args.len() > values.len() {
+            return Err(SlashError::new(&spans[values.len() + 1], &format!("{} has {} fields, but got {} arguments", record_type.name, values.len(), args.len())));
+        }
+        for (i, value) in args.into_iter().enumerate() {
+            values[i] = Some(value);
+        }
+        for named_arg in named {
+            let index = record_type.field_index(&named_arg.name, &named_arg.span)?;
+            if values[index].is_some() {
+                return Err(SlashError::new(&named_arg.span, &format!("Field {} of {} is given more than once", named_arg.name, record_type.name)));
+            }
+            values[index] = Some(named_arg.value);
+        }
+        RecordType::from_values(record_type, values, &spans[0])
+    }
+
+    // Constructs a record from the fields of a table, which must have the fields of the record and no others
+    pub fn from_table(record_type: &Rc<RecordType>, table: &IndexMap<String, Value>, span: &Span) -> Result<Value, SlashError> {
+        let mut values: Vec<Option<Value>> = vec![None; record_type.fields.len()];
+        for (key, value) in table {
+            values[record_type.field_index(key, span)?] = Some(value.clone());
+        }
+        RecordType::from_values(record_type, values, span)
+    }
+
+    fn from_values(record_type: &Rc<RecordType>, values: Vec<Option<Value>>, span: &Span) -> Result<Value, SlashError> {
+        let values = values.into_iter().zip(record_type.fields.iter())
+            .map(|(value, field)| value.ok_or_else(|| SlashError::new(span, &format!("Missing field {} for {}", field, record_type.name))))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::Record(record_type.clone(), Rc::new(Container::new(values))))
+    }
+}
@@ -1,20 +1,59 @@
+use indexmap::IndexMap;
 use std::collections::HashMap;
 use std::rc::Rc;
 use pest::Span;
 use crate::error::SlashError;
-use std::cell::RefCell;
-use crate::function::{Function, FunctionCallResult};
+use std::cell::{Cell, RefCell};
+use std::ops::Deref;
+use crate::function::{Function, FunctionCallResult, NamedArg};
 use crate::closure::Closure;
 use crate::Slash;
+use crate::iterator::Iter;
+use crate::record::{RecordType, Fields};
+use regex::Regex;
 
 #[derive(Debug, Clone)]
 pub enum Value {
-    Table(Rc<RefCell<HashMap<String, Value>>>),
-    List(Rc<RefCell<Vec<Value>>>),
+    Table(Rc<Container<IndexMap<String, Value>>>),
+    List(Rc<Container<Vec<Value>>>),
     Number(f64),
     String(String),
     Function(Function),
     ProcessResult(Option<i32>, String, String),
+    Iterator(Iter),
+    Record(Rc<RecordType>, Fields),
+    Regex(Rc<Regex>),
+    Null,
+}
+
+// The shared contents of a list, table or record. Once frozen the contents can not be modified anymore
+#[derive(Debug)]
+pub struct Container<T> {
+    contents: RefCell<T>,
+    frozen: Cell<bool>,
+}
+
+impl<T> Container<T> {
+    pub fn new(contents: T) -> Container<T> {
+        Container { contents: RefCell::new(contents), frozen: Cell::new(false) }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.get()
+    }
+
+    // Freezes the contents, returns false if they were already frozen
+    fn freeze(&self) -> bool {
+        !self.frozen.replace(true)
+    }
+}
+
+impl<T> Deref for Container<T> {
+    type Target = RefCell<T>;
+
+    fn deref(&self) -> &RefCell<T> {
+        &self.contents
+    }
 }
 
 impl Value {
@@ -37,8 +76,8 @@ impl Value {
                 match rhs {
                     List(rhs_val) => {
                         let mut res: Vec<Value> = lhs_val.borrow().clone();
-                        res.append(&mut rhs_val.borrow_mut());
-                        Ok(List(Rc::new(RefCell::new(res.to_vec()))))
+                        res.extend(rhs_val.borrow().iter().cloned());
+                        Ok(List(Rc::new(Container::new(res))))
                     }
                     _ => Err(SlashError::new(&span, "Add left hand side is List, expected List on right hand side"))
                 }
@@ -86,6 +125,26 @@ impl Value {
         }
     }
 
+    pub fn rem(self, rhs: &Value, span: &Span) -> Result<Value, SlashError> {
+        use Value::*;
+        match self {
+            Number(lhs_val) => {
+                match rhs {
+                    Number(rhs_val) => Ok(Number(lhs_val % rhs_val)),
+                    _ => Err(SlashError::new(&span, "Modulo left hand side is number, expected number on right hand side"))
+                }
+            }
+            _ => Err(SlashError::new(&span, &format!("Modulo not defined on left hand argument value {}", self.value_type())))
+        }
+    }
+
+    pub fn negate(self, span: &Span) -> Result<Value, SlashError> {
+        match self {
+            Value::Number(val) => Ok(Value::Number(-val)),
+            _ => Err(SlashError::new(&span, &format!("Negation not defined on value {}", self.value_type())))
+        }
+    }
+
     pub fn powf(&self, rhs: &Value, span: &Span) -> Result<Value, SlashError> {
         use Value::*;
         match self {
@@ -99,22 +158,77 @@ impl Value {
         }
     }
 
-    pub fn string_from_syntax(parsed: &str) -> Value {
-        Value::String(Value::convert_parsed_string(parsed))
+    pub fn unescape_string(content: &str, span: &Span) -> Result<String, SlashError> {
+        let mut res = String::new();
+        let mut chars = content.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                res.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('n') => res.push('\n'),
+                Some('t') => res.push('\t'),
+                Some('r') => res.push('\r'),
+                Some('0') => res.push('\0'),
+                Some('\\') => res.push('\\'),
+                Some('"') => res.push('"'),
+                Some('\'') => res.push('\''),
+                Some('$') => res.push('$'),
+                Some('x') => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    match u8::from_str_radix(&hex, 16) {
+                        Ok(b) if hex.len() == 2 && b <= 0x7f => res.push(b as char),
+                        _ => return Err(SlashError::new(span, &format!("Invalid escape sequence \\x{}, expected two hex digits with a value of at most 7f", hex)))
+                    }
+                }
+                Some('u') => {
+                    let mut hex = String::new();
+                    let mut terminated = false;
+                    if chars.next() == Some('{') {
+                        for h in chars.by_ref() {
+                            if h == '}' {
+                                terminated = true;
+                                break;
+                            }
+                            hex.push(h);
+                        }
+                    }
+                    match u32::from_str_radix(&hex, 16).ok().and_then(std::char::from_u32) {
+                        Some(ch) if terminated && hex.len() <= 6 => res.push(ch),
+                        _ => return Err(SlashError::new(span, &format!("Invalid unicode escape sequence \\u{{{}}}", hex)))
+                    }
+                }
+                Some(e) => return Err(SlashError::new(span, &format!("Unknown escape sequence \\{}", e))),
+                None => return Err(SlashError::new(span, "Unterminated escape sequence at the end of the string"))
+            }
+        }
+        Ok(res)
     }
 
-    pub fn convert_parsed_string(parsed: &str) -> String {
-        let expanded = String::from(parsed
-            .replace("\\n", "\n")
-            .replace("\\\"", "\"")
-            .replace("\\t", "\t")
-            .replace("\\r", "\r")
-        );
-        String::from(&expanded[1..(expanded.len() - 1)])
+    pub fn dedent(content: &str) -> String {
+        let is_indent = |c: char| c == ' ' || c == '\t';
+        let indent = content.lines()
+            .filter(|l| !l.trim_start_matches(is_indent).is_empty())
+            .map(|l| l.len() - l.trim_start_matches(is_indent).len())
+            .min()
+            .unwrap_or(0);
+
+        let mut res = String::new();
+        for line in content.split_inclusive('\n') {
+            let stripped = line.char_indices()
+                .take(indent)
+                .take_while(|(_, c)| is_indent(*c))
+                .count();
+            res.push_str(&line[stripped..]);
+        }
+        res
     }
 
     fn escape_string(str: &str) -> String {
         String::from(str
+            .replace("\\", "\\\\")
             .replace("\"", "\\\"")
             .replace("\n", "\\n")
             .replace("\t", "\\t")
@@ -129,32 +243,41 @@ impl Value {
             Value::List(l) => l.borrow().len() != 0,
             Value::Table(t) => t.borrow().len() != 0,
             Value::Function(..) => true,
+            Value::Iterator(_) => true,
+            Value::Record(..) => true,
+            Value::Regex(_) => true,
+            Value::Null => false,
             Value::ProcessResult(exit_code, _, _) => if let Some(e) = exit_code { *e == 0 } else { false }
         }
     }
 
-
-    pub fn or(&self, rhs: &Value) -> Value {
-        return bool_to_value(self.is_true() || rhs.is_true());
-    }
-
-    pub fn and(&self, rhs: &Value) -> Value {
-        return bool_to_value(self.is_true() && rhs.is_true());
+    pub fn _equals(&self, rhs: &Value, span: &Span) -> Result<bool, SlashError> {
+        self.equals_visiting(rhs, span, &mut Vec::new())
     }
 
-    pub fn _equals(&self, rhs: &Value, span: &Span) -> Result<bool, SlashError> {
+    // Lists and tables are compared by their contents. The pairs of lists and tables being compared are
+    // kept in visited, so comparing values with cycles ends when a pair is compared again
+    fn equals_visiting(&self, rhs: &Value, span: &Span, visited: &mut Vec<(usize, usize)>) -> Result<bool, SlashError> {
         use Value::*;
+        if let (Some(lhs_address), Some(rhs_address)) = (self.address(), rhs.address()) {
+            if lhs_address == rhs_address || visited.contains(&(lhs_address, rhs_address)) { return Ok(true); }
+            visited.push((lhs_address, rhs_address));
+        }
+        // Any value can be compared to null, and is only equal to it if it is null
+        if let (Null, _) | (_, Null) = (self, rhs) {
+            return Ok(matches!((self, rhs), (Null, Null)));
+        }
         match self {
             Number(lhs_val) => {
                 match rhs {
                     Number(rhs_val) => Ok(lhs_val == rhs_val),
-                    _ => self.type_mismatch_error(&rhs, span)
+                    _ => self.type_mismatch_error(rhs, span)
                 }
             }
             String(lhs_val) => {
                 match rhs {
                     String(rhs_val) => Ok(lhs_val.eq(rhs_val)),
-                    _ => self.type_mismatch_error(&rhs, span)
+                    _ => self.type_mismatch_error(rhs, span)
                 }
             }
             List(lhs_val) => {
@@ -166,23 +289,172 @@ impl Value {
                             Ok(false)
                         } else {
                             for i in 0..rhs_val.len() {
-                                if !rhs_val[i]._equals(&lhs_val[i], span)? {
+                                if !lhs_val[i].same_type(&rhs_val[i]) || !lhs_val[i].equals_visiting(&rhs_val[i], span, visited)? {
                                     return Ok(false);
                                 }
                             }
                             Ok(true)
                         }
                     }
-                    _ => self.type_mismatch_error(&rhs, span)
+                    _ => self.type_mismatch_error(rhs, span)
                 }
             }
-            // Table(lhs_val) => {
-            //     match rhs {
-            //         Table(rhs_val) => lhs_val.eq(&rhs_val),
-            //         _ => panic!("Type mismatch in comparison")
-            //     }
-            // }
-            _ => self.type_mismatch_error(&rhs, span)
+            Table(lhs_val) => {
+                match rhs {
+                    Table(rhs_val) => {
+                        let lhs_val = lhs_val.borrow();
+                        let rhs_val = rhs_val.borrow();
+                        if rhs_val.len() != lhs_val.len() {
+                            return Ok(false);
+                        }
+                        for (key, value) in lhs_val.iter() {
+                            match rhs_val.get(key) {
+                                Some(rhs_value) if value.same_type(rhs_value) && value.equals_visiting(rhs_value, span, visited)? => {}
+                                _ => return Ok(false)
+                            }
+                        }
+                        Ok(true)
+                    }
+                    _ => self.type_mismatch_error(rhs, span)
+                }
+            }
+            Record(lhs_type, lhs_val) => {
+                match rhs {
+                    Record(rhs_type, rhs_val) if Rc::ptr_eq(lhs_type, rhs_type) => {
+                        for (lhs_field, rhs_field) in lhs_val.borrow().iter().zip(rhs_val.borrow().iter()) {
+                            if !lhs_field.same_type(rhs_field) || !lhs_field.equals_visiting(rhs_field, span, visited)? {
+                                return Ok(false);
+                            }
+                        }
+                        Ok(true)
+                    }
+                    _ => self.type_mismatch_error(rhs, span)
+                }
+            }
+            Function(lhs_val) => {
+                match rhs {
+                    Function(rhs_val) => Ok(lhs_val.same(rhs_val)),
+                    _ => self.type_mismatch_error(rhs, span)
+                }
+            }
+            Regex(lhs_val) => {
+                match rhs {
+                    Regex(rhs_val) => Ok(lhs_val.as_str() == rhs_val.as_str()),
+                    _ => self.type_mismatch_error(rhs, span)
+                }
+            }
+            _ => self.type_mismatch_error(rhs, span)
+        }
+    }
+
+    // Identity of values. Lists, tables, functions, iterators and regular expressions are the same if they are the same object,
+    // other values if they are equal
+    pub fn same(&self, rhs: &Value) -> bool {
+        use Value::*;
+        match (self, rhs) {
+            (List(_), List(_)) | (Table(_), Table(_)) | (Record(..), Record(..)) => self.address() == rhs.address(),
+            (Function(lhs_val), Function(rhs_val)) => lhs_val.same(rhs_val),
+            (Iterator(lhs_val), Iterator(rhs_val)) => Rc::ptr_eq(lhs_val, rhs_val),
+            (Regex(lhs_val), Regex(rhs_val)) => Rc::ptr_eq(lhs_val, rhs_val),
+            (Number(lhs_val), Number(rhs_val)) => lhs_val == rhs_val,
+            (String(lhs_val), String(rhs_val)) => lhs_val == rhs_val,
+            (Null, Null) => true,
+            _ => false
+        }
+    }
+
+    pub fn same_type(&self, rhs: &Value) -> bool {
+        self.value_type() == rhs.value_type()
+    }
+
+    // The address of the contents of a list, table or record, which identifies it
+    pub fn address(&self) -> Option<usize> {
+        match self {
+            Value::List(l) => Some(Rc::as_ptr(l) as *const () as usize),
+            Value::Table(t) => Some(Rc::as_ptr(t) as *const () as usize),
+            Value::Record(_, fields) => Some(Rc::as_ptr(fields) as *const () as usize),
+            _ => None
+        }
+    }
+
+    // Freezes a list, table or record and everything it contains
+    pub fn freeze(&self) {
+        let newly_frozen = match self {
+            Value::List(l) => l.freeze(),
+            Value::Table(t) => t.freeze(),
+            Value::Record(_, fields) => fields.freeze(),
+            _ => false
+        };
+        if !newly_frozen { return; }
+        match self {
+            Value::List(l) => l.borrow().iter().for_each(|v| v.freeze()),
+            Value::Table(t) => t.borrow().values().for_each(|v| v.freeze()),
+            Value::Record(_, fields) => fields.borrow().iter().for_each(|v| v.freeze()),
+            _ => {}
+        }
+    }
+
+    pub fn check_not_frozen(&self, span: &Span) -> Result<(), SlashError> {
+        let frozen = match self {
+            Value::List(l) => l.is_frozen(),
+            Value::Table(t) => t.is_frozen(),
+            Value::Record(_, fields) => fields.is_frozen(),
+            _ => false
+        };
+        if frozen {
+            Err(SlashError::new(span, &format!("Cannot modify a frozen {}", self.value_type())))
+        } else {
+            Ok(())
+        }
+    }
+
+    // A copy of a list or table that shares the elements with the original
+    pub fn copy(&self) -> Value {
+        match self {
+            Value::List(l) => Value::List(Rc::new(Container::new(l.borrow().clone()))),
+            Value::Table(t) => Value::Table(Rc::new(Container::new(t.borrow().clone()))),
+            Value::Record(record_type, fields) => Value::Record(record_type.clone(), Rc::new(Container::new(fields.borrow().clone()))),
+            _ => self.clone()
+        }
+    }
+
+    // A copy of a list or table and all the lists and tables it contains. A list or table that is
+    // contained more than once is copied once, so shared parts and cycles are kept in the copy
+    pub fn deep_copy(&self) -> Value {
+        self.deep_copy_visiting(&mut HashMap::new())
+    }
+
+    fn deep_copy_visiting(&self, copies: &mut HashMap<usize, Value>) -> Value {
+        let address = match self.address() {
+            Some(address) => address,
+            None => return self.clone()
+        };
+        if let Some(copy) = copies.get(&address) {
+            return copy.clone();
+        }
+        match self {
+            Value::List(l) => {
+                let copy = Rc::new(Container::new(Vec::new()));
+                copies.insert(address, Value::List(copy.clone()));
+                let elements = l.borrow().iter().map(|v| v.deep_copy_visiting(copies)).collect();
+                *copy.borrow_mut() = elements;
+                Value::List(copy)
+            }
+            Value::Table(t) => {
+                let copy = Rc::new(Container::new(IndexMap::new()));
+                copies.insert(address, Value::Table(copy.clone()));
+                let fields = t.borrow().iter().map(|(k, v)| (k.clone(), v.deep_copy_visiting(copies))).collect();
+                *copy.borrow_mut() = fields;
+                Value::Table(copy)
+            }
+            Value::Record(record_type, fields) => {
+                let copy = Rc::new(Container::new(Vec::new()));
+                copies.insert(address, Value::Record(record_type.clone(), copy.clone()));
+                let values = fields.borrow().iter().map(|v| v.deep_copy_visiting(copies)).collect();
+                *copy.borrow_mut() = values;
+                Value::Record(record_type.clone(), copy)
+            }
+            _ => unreachable!()
         }
     }
 
@@ -213,12 +485,22 @@ impl Value {
                     _ => self.type_mismatch_error(&rhs, span)
                 }
             }
-            // List(lhs_val) => {
-            //     match rhs {
-            //         List(rhs_val) => lhs_val.lt(&rhs_val),
-            //         _ => panic!("Type mismatch in comparison")
-            //     }
-            // },
+            // Lists are ordered by the first element that differs, or by length if one is a prefix of the other
+            List(lhs_val) => {
+                match rhs {
+                    List(rhs_val) => {
+                        let lhs_val = lhs_val.borrow();
+                        let rhs_val = rhs_val.borrow();
+                        for (lhs_element, rhs_element) in lhs_val.iter().zip(rhs_val.iter()) {
+                            if !lhs_element._equals(rhs_element, span)? {
+                                return lhs_element._less_than(rhs_element, span);
+                            }
+                        }
+                        Ok(lhs_val.len() < rhs_val.len())
+                    }
+                    _ => self.type_mismatch_error(rhs, span)
+                }
+            }
             _ => self.type_mismatch_error(&rhs, span)
         }
     }
@@ -260,22 +542,42 @@ impl Value {
             Value::Table(_) => "Table",
             Value::ProcessResult(_, _, _) => "Process result",
             Value::Function(_) => "Function",
+            Value::Iterator(_) => "Iterator",
+            Value::Record(record_type, _) => &record_type.name,
+            Value::Regex(_) => "Regex",
+            Value::Null => "Null",
         }
     }
 
     pub fn to_json(&self) -> String {
-        match self {
+        self.to_json_visiting(&mut Vec::new())
+    }
+
+    // The lists and tables being converted are kept in visiting, a list or table that contains
+    // itself is converted to "<<cycle>>" where it appears inside itself
+    fn to_json_visiting(&self, visiting: &mut Vec<usize>) -> String {
+        if let Some(address) = self.address() {
+            if visiting.contains(&address) { return "\"<<cycle>>\"".to_owned(); }
+            visiting.push(address);
+        }
+        let json = match self {
             Value::Number(f) => format!("{}", f),
-            Value::String(s) => format!("\"{}\"", Value::escape_string(&s)),
+            Value::String(s) => format!("\"{}\"", Value::escape_string(s)),
             Value::List(l) => {
                 let mut s = String::from("");
-                l.borrow().iter().for_each(|v| s.push_str(&format!(", {}", v.to_json())));
+                l.borrow().iter().for_each(|v| s.push_str(&format!(", {}", v.to_json_visiting(visiting))));
                 format!("[{}]", if s.len() > 2 { &s[2..] } else { "" })
             }
             Value::Table(t_r) => {
                 let t = t_r.borrow();
                 let mut s = String::from("");
-                t.keys().for_each(|k| s.push_str(&format!(", \"{}\": {}", Value::escape_string(k), t.get(k).unwrap().to_json())));
+                t.iter().for_each(|(k, v)| s.push_str(&format!(", \"{}\": {}", Value::escape_string(k), v.to_json_visiting(visiting))));
+                format!("{{{}}}", if s.len() > 2 { &s[2..] } else { "" })
+            }
+            Value::Record(record_type, fields) => {
+                let mut s = String::from("");
+                record_type.fields.iter().zip(fields.borrow().iter())
+                    .for_each(|(k, v)| s.push_str(&format!(", \"{}\": {}", Value::escape_string(k), v.to_json_visiting(visiting))));
                 format!("{{{}}}", if s.len() > 2 { &s[2..] } else { "" })
             }
             Value::ProcessResult(exitcode, stdout, stderr) => {
@@ -287,8 +589,15 @@ impl Value {
                 }
                 format!("{} \"stderr\": {}, \"stdout\": {} }}", pre, stderr, stdout)
             }
-            Value::Function(_) => format!("\"<<function>>\"")
+            Value::Function(_) => format!("\"<<function>>\""),
+            Value::Iterator(_) => "\"<<iterator>>\"".to_owned(),
+            Value::Regex(regex) => format!("\"{}\"", Value::escape_string(regex.as_str())),
+            Value::Null => "null".to_owned()
+        };
+        if self.address().is_some() {
+            visiting.pop();
         }
+        json
     }
 
     fn _to_s32(&self, span: &Span) -> Result<i32, SlashError> {
@@ -299,14 +608,32 @@ impl Value {
         }
     }
 
+    // A negative index counts from the end, -1 is the last element. The index into a string is the
+    // index of a character, not of a byte
+    fn element_index(&self, index: &Value, len: usize, span: &Span) -> Result<Option<usize>, SlashError> {
+        let index = index._to_s32(span)?;
+        let resolved = if index < 0 { index + len as i32 } else { index };
+        Ok(if 0 <= resolved && resolved < len as i32 { Some(resolved as usize) } else { None })
+    }
+
+    fn index_out_of_bounds(len: usize, index: &Value, span: &Span) -> SlashError {
+        SlashError::new(span, &format!("Index out of bounds. Value length is {} index was {}", len, index.to_string()))
+    }
+
     pub fn lookup_by_index(&self, index: &Value, span: &Span) -> Result<Value, SlashError> {
         match self {
             Value::List(l) => {
-                let index = index._to_s32(span)?;
-                if 0 <= index &&  index < l.borrow().len() as i32 {
-                    Ok(l.borrow()[index as usize].clone())
-                } else {
-                    Err(SlashError::new(&span, &format!("Index out of bounds. Value length is {} index was {}", l.borrow().len(), index)))
+                let len = l.borrow().len();
+                match self.element_index(index, len, span)? {
+                    Some(i) => Ok(l.borrow()[i].clone()),
+                    None => Err(Value::index_out_of_bounds(len, index, span))
+                }
+            }
+            Value::String(s) => {
+                let len = s.chars().count();
+                match self.element_index(index, len, span)? {
+                    Some(i) => Ok(Value::String(s.chars().nth(i).unwrap().to_string())),
+                    None => Err(Value::index_out_of_bounds(len, index, span))
                 }
             }
             Value::Table(t) => {
@@ -320,28 +647,155 @@ impl Value {
                     Err(SlashError::new(&span, &format!("Index value not a string, but a {}", index.value_type())))
                 }
             }
-            _ => Err(SlashError::new(&span, &format!("Trying to index into non-indexable type {}, expected List or Table", self.value_type())))
+            Value::Record(record_type, fields) => {
+                if let Value::String(s) = index {
+                    Ok(fields.borrow()[record_type.field_index(s, span)?].clone())
+                } else {
+                    Err(SlashError::new(span, &format!("Index value not a string, but a {}", index.value_type())))
+                }
+            }
+            _ => Err(SlashError::new(&span, &format!("Trying to index into non-indexable type {}, expected List, String, Table or record", self.value_type())))
         }
     }
 
-    pub fn slice(&self, from: &Value, to: &Value, span: &Span) -> Result<Value, SlashError> {
+    // Looks up an index like lookup_by_index, but a missing entry, or indexing into null, gives null
+    pub fn optional_lookup_by_index(&self, index: &Value, span: &Span) -> Result<Value, SlashError> {
+        match (self, index) {
+            (Value::Null, _) => Ok(Value::Null),
+            (Value::List(l), Value::Number(_)) => {
+                let len = l.borrow().len();
+                Ok(self.element_index(index, len, span)?.map(|i| l.borrow()[i].clone()).unwrap_or(Value::Null))
+            }
+            (Value::String(s), Value::Number(_)) => {
+                let len = s.chars().count();
+                Ok(self.element_index(index, len, span)?.map(|i| Value::String(s.chars().nth(i).unwrap().to_string())).unwrap_or(Value::Null))
+            }
+            (Value::Table(t), Value::String(s)) => Ok(t.borrow().get(s).cloned().unwrap_or(Value::Null)),
+            (Value::Record(record_type, fields), Value::String(s)) => {
+                Ok(record_type.fields.iter().position(|f| f == s).map(|i| fields.borrow()[i].clone()).unwrap_or(Value::Null))
+            }
+            _ => self.lookup_by_index(index, span)
+        }
+    }
+
+    pub fn assign_by_index(&self, index: &Value, value: Value, span: &Span) -> Result<(), SlashError> {
+        self.check_not_frozen(span)?;
         match self {
             Value::List(l) => {
-                let from = from._to_s32(span)?;
-                let to = to._to_s32(span)?;
-                if 0 <= from && from <= to && to <= l.borrow().len() as i32 {
-                    Ok(Value::List(Rc::new(RefCell::new(l.borrow()[from as usize..to as usize].iter().map(|v| {v.clone()}).collect()))))
+                let len = l.borrow().len();
+                match self.element_index(index, len, span)? {
+                    Some(i) => {
+                        l.borrow_mut()[i] = value;
+                        Ok(())
+                    }
+                    None => Err(Value::index_out_of_bounds(len, index, span))
+                }
+            }
+            Value::Table(t) => {
+                if let Value::String(s) = index {
+                    t.borrow_mut().insert(s.clone(), value);
+                    Ok(())
                 } else {
-                    Err(SlashError::new(&span, &format!("Slice variables out of bound 0 <= from: {} <= to: {} < length: {}", from, to, l.borrow().len())))
+                    Err(SlashError::new(&span, &format!("Index value not a string, but a {}", index.value_type())))
                 }
             }
-            _ => Err(SlashError::new(&span, &format!("Trying to slice on a non-list type {}, expected List or Table", self.value_type())))
+            Value::Record(record_type, fields) => {
+                if let Value::String(s) = index {
+                    fields.borrow_mut()[record_type.field_index(s, span)?] = value;
+                    Ok(())
+                } else {
+                    Err(SlashError::new(span, &format!("Index value not a string, but a {}", index.value_type())))
+                }
+            }
+            _ => Err(SlashError::new(&span, &format!("Trying to assign into non-indexable type {}, expected List, Table or record", self.value_type())))
+        }
+    }
+
+    pub fn push(&self, value: Value, span: &Span) -> Result<(), SlashError> {
+        self.check_not_frozen(span)?;
+        match self {
+            Value::List(l) => {
+                l.borrow_mut().push(value);
+                Ok(())
+            }
+            _ => Err(SlashError::new(&span, &format!("Trying to push onto non-list type {}, expected List", self.value_type())))
+        }
+    }
+
+    // The range of a slice from..to of a value of length len. A null bound is the start or the end of the value,
+    // and negative bounds count from the end
+    fn slice_range(from: &Value, to: &Value, len: usize, span: &Span) -> Result<(usize, usize), SlashError> {
+        let bound = |bound: &Value, open: i32| -> Result<i32, SlashError> {
+            match bound {
+                Value::Null => Ok(open),
+                _ => bound._to_s32(span).map(|b| if b < 0 { b + len as i32 } else { b })
+            }
+        };
+        let from = bound(from, 0)?;
+        let to = bound(to, len as i32)?;
+        if 0 <= from && from <= to && to <= len as i32 {
+            Ok((from as usize, to as usize))
+        } else {
+            Err(SlashError::new(span, &format!("Slice variables out of bound 0 <= from: {} <= to: {} < length: {}", from, to, len)))
+        }
+    }
+
+    pub fn slice(&self, from: &Value, to: &Value, span: &Span) -> Result<Value, SlashError> {
+        match self {
+            Value::List(l) => {
+                let (from, to) = Value::slice_range(from, to, l.borrow().len(), span)?;
+                Ok(Value::List(Rc::new(Container::new(l.borrow()[from..to].to_vec()))))
+            }
+            Value::String(s) => {
+                let (from, to) = Value::slice_range(from, to, s.chars().count(), span)?;
+                Ok(Value::String(s.chars().skip(from).take(to - from).collect()))
+            }
+            _ => Err(SlashError::new(span, &format!("Trying to slice on a non-list type {}, expected List or String", self.value_type())))
+        }
+    }
+
+    // Replaces the elements of a list in the slice from..to with the elements of a list
+    pub fn assign_slice(&self, from: &Value, to: &Value, value: Value, span: &Span) -> Result<(), SlashError> {
+        self.check_not_frozen(span)?;
+        match (self, &value) {
+            (Value::List(l), Value::List(elements)) => {
+                let (from, to) = Value::slice_range(from, to, l.borrow().len(), span)?;
+                let elements = elements.borrow().clone();
+                l.borrow_mut().splice(from..to, elements);
+                Ok(())
+            }
+            (Value::List(_), _) => Err(SlashError::new(span, &format!("Expected a List to assign to a slice, but got a {}", value.value_type()))),
+            _ => Err(SlashError::new(span, &format!("Trying to assign to a slice of non-list type {}, expected List", self.value_type())))
+        }
+    }
+
+    // Strings can not be changed, so assigning to an index or a slice of a string gives a new string with the
+    // characters from..to replaced by a string
+    pub fn replace_string_slice(&self, from: &Value, to: &Value, value: &Value, span: &Span) -> Result<Value, SlashError> {
+        match (self, value) {
+            (Value::String(s), Value::String(replacement)) => {
+                let (from, to) = Value::slice_range(from, to, s.chars().count(), span)?;
+                let mut replaced: String = s.chars().take(from).collect();
+                replaced.push_str(replacement);
+                replaced.extend(s.chars().skip(to));
+                Ok(Value::String(replaced))
+            }
+            _ => Err(SlashError::new(span, &format!("Expected a String to assign into a String, but got a {}", value.value_type())))
+        }
+    }
+
+    // The slice of a string covering the character at an index
+    pub fn string_index_range(&self, index: &Value, span: &Span) -> Result<(Value, Value), SlashError> {
+        let len = if let Value::String(s) = self { s.chars().count() } else { 0 };
+        match self.element_index(index, len, span)? {
+            Some(i) => Ok((Value::Number(i as f64), Value::Number(i as f64 + 1.0))),
+            None => Err(Value::index_out_of_bounds(len, index, span))
         }
     }
 
-    pub fn invoke(&self, args: Vec<Value>, spans: Vec<Span>, closure: &mut Closure, slash: &Slash) -> Result<FunctionCallResult, SlashError> {
+    pub fn invoke(&self, name: &str, args: Vec<Value>, spans: Vec<Span>, named: Vec<NamedArg>, closure: &mut Closure, slash: &Slash) -> Result<FunctionCallResult, SlashError> {
         if let Value::Function(function) = self {
-            function.invoke("(expr)", args, spans, closure, slash)
+            function.invoke_with_named(name, args, spans, named, closure, slash)
         } else {
             Err(SlashError::new(&spans[0], &format!("The left hand side does not evaluate to a function")))
         }
@@ -356,6 +810,7 @@ impl ToString for Value {
     fn to_string(&self) -> String {
         match self {
             Value::String(val) => String::from(val),
+            Value::Regex(regex) => String::from(regex.as_str()),
             _ => self.to_json()
         }
     }
@@ -0,0 +1,152 @@
+mod common;
+
+fn assert_errors(src: &str, expected: &[&str]) {
+    let errors = common::check(src);
+    assert_eq!(expected.len(), errors.len(), "Expected {} errors, but got:\n{}", expected.len(), errors.join("\n"));
+    for (expected, error) in expected.iter().zip(errors.iter()) {
+        if !error.contains(expected) {
+            panic!("Expected error containing \"{}\", but got:\n{}", expected, error);
+        }
+    }
+}
+
+#[test]
+fn test_check_annotations() {
+    assert_errors(r##"
+    let x: Number = 1
+    let s: String = "a" + "b"
+    let l: List = [1, 2]
+    let t: Table = {a: 1}
+    let f: Function = |a| a
+    let any: Any = 1
+    x = 2
+    "##, &[]);
+
+    assert_errors(r##"
+    let x: Number = "a"
+    let y: List = 1
+    x = [1]
+    let z: Numbr = 1
+    "##, &[
+        "Expected x to be a Number, but it is a String",
+        "Expected y to be a List, but it is a Number",
+        "Expected x to be a Number, but it is a List",
+        "Unknown type Numbr",
+    ]);
+
+    assert_errors(r##"
+    struct Point { x, y }
+    match Point(1, 2) {
+        is Point p => { let n: Number = p }
+        is Any a => { }
+        is Pont p => { }
+    }
+    "##, &[
+        "Expected n to be a Number, but it is a Point",
+        "Unknown type Pont",
+    ]);
+
+    // The type of an unannotated variable is unknown once another type is assigned
+    assert_errors(r##"
+    let x = 1
+    x = "a"
+    let y: Number = x
+    let z: Number = lookup_env_var("HOME")
+    "##, &[]);
+}
+
+#[test]
+fn test_check_functions() {
+    assert_errors(r##"
+    function f(x: String, n: Number = 1): List {
+        return [x, n]
+    }
+    let l: List = f("a")
+    f("a", 2)
+    f(x: "a", n: 2)
+    "##, &[]);
+
+    assert_errors(r##"
+    function f(x: String): Number {
+        return x
+    }
+    f(1)
+    f()
+    f("a", 2)
+    f(y: "a")
+    let s: String = f("a")
+    "##, &[
+        "Expected the return value of function f to be a Number, but it is a String",
+        "Expected argument x of function f to be a String, but it is a Number",
+        "Missing argument x for function call f",
+        "Parameter mismatch for function call f, expected 1 arguments but got 2",
+        "Function f has no parameter named y",
+        "Missing argument x for function call f",
+        "Expected s to be a String, but it is a Number",
+    ]);
+
+    assert_errors(r##"
+    let n = 1
+    n(2)
+    len(1, 2)
+    let s: Number = trim(" a ")
+    split(1, ",")
+    "##, &[
+        "The left hand side does not evaluate to a function",
+        "Parameter mismatch for function call len, expected 1 arguments but got 2",
+        "Expected s to be a Number, but it is a String",
+        "Expected argument 1 of function split to be a String, but it is a Number",
+    ]);
+}
+
+#[test]
+fn test_check_expressions() {
+    assert_errors(r##"
+    let a = 1 - "a"
+    let b = [1] + 1
+    if 1 == "1" { print("never") }
+    let c = 1 + 2 * 3
+    let d = "a" + "b"
+    "##, &[
+        "Type mismatch, cannot apply - to Number and String",
+        "Type mismatch, cannot apply + to List and Number",
+        "Type mismatch in comparison. Cannot compare Number to String",
+    ]);
+
+    assert_errors(r##"
+    struct Point { x, y }
+    let p: Point = Point(1, 2)
+    print(p.x)
+    print(p.z)
+    p.w = 1
+    let q: Point = 1
+    let r = Point(1)
+    "##, &[
+        "Point has no field named z",
+        "Point has no field named w",
+        "Expected q to be a Point, but it is a Number",
+        "Missing argument y for function call Point",
+    ]);
+}
+
+#[test]
+fn test_check_does_not_run() {
+    assert_errors(r##"
+    exit(1)
+    false | true
+    for x in [1, 2] { let y: Number = x }
+    echo "a" $> result
+    let s: String = stdout(result)
+    "##, &[]);
+}
+
+#[test]
+fn test_check_include() {
+    assert_errors(r##"
+    include("sub/typed.sl")
+    let s: String = greeting("world")
+    greeting(1)
+    "##, &[
+        "Expected argument name of function greeting to be a String, but it is a Number",
+    ]);
+}
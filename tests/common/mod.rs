@@ -14,6 +14,25 @@ pub fn run(src: &str, expected_output: &str) {
     }
 }
 
+#[allow(dead_code)]
+pub fn run_error(src: &str, expected_error: &str) {
+    let res = slash::Slash::new(src,
+                                Box::new(RefCell::new(io::sink())),
+                                Box::new(RefCell::new(io::sink())),
+                                PathBuf::from("tests/testfiles"),
+                                vec!()
+    ).run();
+    match res {
+        Ok(()) => panic!("Expected error containing \"{}\", but the script succeeded", expected_error),
+        Err(err) => {
+            let err = err.to_string();
+            if !err.contains(expected_error) {
+                panic!("Expected error containing \"{}\", but got:\n{}", expected_error, err);
+            }
+        }
+    }
+}
+
 fn do_run(src: &str) -> io::Result<(String, String)> {
     let mut tmp_stderr = NamedTempFile::new()?;
     let mut tmp_stdout = NamedTempFile::new()?;
@@ -32,4 +51,13 @@ fn do_run(src: &str) -> io::Result<(String, String)> {
     let mut stderr_res = String::new();
     tmp_stderr.read_to_string(&mut stderr_res).expect("Failed to read stderr result into string");
     Ok((stdout_res, stderr_res))
-}
\ No newline at end of file
+}
+#[allow(dead_code)]
+pub fn check(src: &str) -> Vec<String> {
+    slash::Slash::new(src,
+                      Box::new(RefCell::new(io::sink())),
+                      Box::new(RefCell::new(io::sink())),
+                      PathBuf::from("tests/testfiles"),
+                      vec!()
+    ).check().iter().map(|e| e.to_string()).collect()
+}
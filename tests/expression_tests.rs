@@ -138,4 +138,316 @@ fn test_functional() {
     print(t[0]("pass"))
     "##, "pass");
 
-}
\ No newline at end of file
+}
+#[test]
+fn test_string_interpolation() {
+    common::run(r##"
+    let x = 1
+    let y = "pass"
+    print("x=${x} y=${y}")
+    "##, "x=1 y=pass");
+
+    common::run(r##"
+    let l = [1, 2]
+    print("${ l[0] + l[1] } ${l} ${ to_str(len(l)) + "!" }")
+    "##, "3 [1, 2] 2!");
+
+    common::run(r##"
+    print("\${x} $x ${"$"}{x}")
+    "##, "${x} $x ${x}");
+
+    common::run(r##"
+    let p = "pa"
+    echo -n "${p + "ss"}"
+    "##, "pass");
+}
+
+#[test]
+fn test_string_escapes() {
+    common::run(r##"print("a\\nb")"##, "a\\nb");
+    common::run(r##"print("\x41\u{e6}\u{1F600}\'\"")"##, "Aæ😀'\"");
+    common::run(r##"print(len("\0"))"##, "1");
+    common::run(r##"print(json_stringify("a\\b"))"##, r##""a\\b""##);
+}
+
+#[test]
+fn test_raw_string() {
+    common::run(r##"print(r"c:\new\${x}")"##, r##"c:\new\${x}"##);
+    common::run(r###"print(r#"say "pass""#)"###, r##"say "pass""##);
+    common::run(r##"echo -n r"\pass""##, r##"\pass"##);
+}
+
+#[test]
+fn test_heredoc() {
+    common::run(r##"
+    let sql = <<SQL
+        SELECT *
+          FROM t
+
+        WHERE a = "\n"
+        SQL
+    print(sql)
+    "##, "SELECT *\n  FROM t\n\nWHERE a = \"\\n\"\n");
+
+    common::run(r##"
+    let empty = <<EOF
+    EOF
+    print(len(empty))
+    "##, "0");
+
+    common::run("
+    let wide = <<EOF
+        a
+      \u{3000}
+    EOF
+    print(wide)
+    ", "  a\n\u{3000}\n");
+}
+
+#[test]
+fn test_operators() {
+    common::run("print(7 % 3, -7 % 3)", "1 -1");
+    common::run("print(1 <= 2, 2 <= 2, 3 <= 2)", "1 1 0");
+    common::run("print(1 >= 2, 2 >= 2, 3 >= 2)", "0 1 1");
+    common::run(r##"print("a" <= "b", "b" >= "c")"##, "1 0");
+    common::run(r##"
+    let x = 3
+    let l = [1, 2]
+    print(-x, -x * 2, 1 - -x, -(x + 1), -l[1], - len(l))
+    "##, "-3 -6 4 -4 -2 -2");
+    common::run(r##"
+    let x = 2
+    print(-x^2, -2^2, (-x)^2, 2^-1, 2^3^2, -x^2 * 3)
+    "##, "-4 -4 4 0.5 512 -12");
+}
+
+#[test]
+fn test_short_circuit() {
+    common::run(r##"
+    function fail() { print("fail") return 1 }
+    if 0 && fail() { print("fail") }
+    if 1 || fail() { print("pass") }
+    "##, "pass");
+
+    common::run(r##"
+    let maybe = ""
+    let x = maybe || "default"
+    let y = "value" || "default"
+    print(x, y)
+    "##, "default value");
+
+    common::run(r##"
+    print(0 && "x", 1 && "y", [] || 0, 1 && 2 && 3, 0 || "" || "last")
+    "##, "0 y 0 3 last");
+
+    common::run(r##"
+    let l = []
+    if len(l) > 0 && l[0] == 1 { print("fail") } else { print("pass") }
+    "##, "pass");
+}
+
+#[test]
+fn test_nested_access() {
+    common::run(r##"
+    let t = { "l": [ { "a": "pass" } ], "f": || { return "pass" } }
+    print(t.l[0].a, t["l"][0]["a"], t.f())
+    "##, "pass pass pass");
+}
+
+#[test]
+fn test_method_call() {
+    common::run(r##"print("pa ss".split(" ").join(""))"##, "pass");
+    common::run(r##"print([1, 2, 3].len(), "  pass ".trim().len())"##, "3 4");
+    common::run(r##"
+    function twice(x) { return x * 2 }
+    let n = 21
+    print(n.twice(), (1 + 2).twice())
+    "##, "42 6");
+    common::run(r##"
+    let t = { "greet": |name| { return "hello " + name }, "len": 7 }
+    print(t.greet("pass"), t.len, len(t))
+    "##, "hello pass 7 2");
+}
+
+#[test]
+fn test_if_and_match_expressions() {
+    common::run(r##"
+    let x = 5
+    let sign = if x < 0 { "negative" } else if x == 0 { "zero" } else { "positive" }
+    print(sign, if x > 3 { x * 2 } else { 0 })
+    "##, "positive 10");
+
+    common::run(r##"
+    function level(code) {
+      return match code { 0 => "ok", 1 -> 3 => "warn", _ => "fail" }
+    }
+    print(level(0), level(2), level(9))
+    "##, "ok warn fail");
+
+    common::run(r##"
+    let msg = match ["deploy", "prod"] {
+      ["deploy", target] if target == "prod" => "deploying " + target
+      [cmd, ...] => "unknown " + cmd
+    }
+    print(msg)
+    "##, "deploying prod");
+
+    common::run_error(r##"
+    let x = match 5 { 0 => "zero", 1 => "one" }
+    "##, "No match arm matched the value 5");
+}
+
+#[test]
+fn test_table_order() {
+    common::run(r##"
+    let t = { "zeta": 1, "alpha": 2, "mid": 3 }
+    t.beta = 4
+    t.zeta = 5
+    println(t)
+    println(json_stringify(json_parse("{\"y\": 1, \"x\": {\"b\": 2, \"a\": 3}}")))
+    println(entries({ "b": 1, "a": 2 }))
+    "##, "{\"zeta\": 5, \"alpha\": 2, \"mid\": 3, \"beta\": 4}\n{\"y\": 1, \"x\": {\"b\": 2, \"a\": 3}}\n[[\"b\", 1], [\"a\", 2]]\n");
+}
+
+#[test]
+fn test_copy_and_equality() {
+    common::run(r##"
+    let t = { "a": [1, 2], "b": { "c": 3 } }
+    let shallow = copy(t)
+    let deep = deep_copy(t)
+    shallow.b.c = 4
+    deep.a[] = 5
+    print(t, same(shallow.a, t.a), same(deep.a, t.a), same(t, t), same(copy(t), t))
+    "##, r#"{"a": [1, 2], "b": {"c": 4}} 1 0 1 0"#);
+
+    common::run(r##"
+    print({ "a": 1, "b": [2] } == { "b": [2], "a": 1 }, { "a": 1 } == { "a": 2 }, { "a": 1 } != { "a": 1, "b": 2 })
+    print({ "a": 1 } == { "a": "1" })
+    print([1] == ["x"], [1, [2]] != [1, ["2"]])
+    let f = |x| x
+    print(f == f, f == |x| x, print == print)
+    "##, "1 0 100 11 0 1");
+
+    common::run(r##"
+    print([1, 2] < [1, 3], [1, 2] < [1, 2, 0], [2] > [1, 5], [1, 2] <= [1, 2], ["b"] < ["a", "z"])
+    "##, "1 1 1 1 0");
+
+    common::run(r##"
+    let t = { "name": "loop" }
+    t.self = t
+    let l = [1]
+    l[] = l
+    print(t, l, t == t, len(to_str(t)) > 0)
+    let c = deep_copy(t)
+    print(same(c.self, c), same(c.self, t), c == t)
+    "##, r#"{"name": "loop", "self": "<<cycle>>"} [1, "<<cycle>>"] 1 11 0 1"#);
+
+    common::run_error(r##"print([1] < ["a"])"##, "Cannot compare Number to String");
+}
+
+#[test]
+fn test_records() {
+    common::run(r##"
+    struct Server { host, port }
+    let s = Server("localhost", 8080)
+    let t = Server(port: 443, host: "example.com")
+    s.port += 1
+    t["host"] = "example.org"
+    print(s.host, s.port, t, s == Server("localhost", 8081), s == t)
+    "##, r#"localhost 8081 {"host": "example.org", "port": 443} 1 0"#);
+
+    common::run(r##"
+    struct Point {
+        x
+        y
+    }
+    let p = Point(1, 2)
+    let q = copy(p)
+    q.x = 5
+    print(p, same(p, p), same(p, q), to_str(p) == json_stringify(p))
+    print(json_parse("{\"y\": 4, \"x\": 3}", Point).y)
+    print(json_parse("[{\"x\": 1, \"y\": 2}]", Point)[0] == p)
+    "##, r#"{"x": 1, "y": 2} 1 0 141"#);
+
+    common::run_error(r##"struct Server { host, port }; print(Server("a", 1).hots)"##, "Server has no field named hots");
+    common::run_error(r##"struct Server { host, port }; let s = Server("a", 1); s.user = "me""##, "Server has no field named user");
+    common::run_error(r##"struct Server { host, port }; Server("a")"##, "Missing field port for Server");
+    common::run_error(r##"struct Server { host, port }; Server("a", 1, 2)"##, "Server has 2 fields, but got 3 arguments");
+    common::run_error(r##"struct Server { host, port }; Server("a", host: "b")"##, "Field host of Server is given more than once");
+    common::run_error(r##"struct Server { host, host }"##, "Duplicate field host");
+    common::run_error(r##"struct Server { host, port }; json_parse("{\"host\": \"a\", \"port\": 1, \"user\": 2}", Server)"##, "Server has no field named user");
+    common::run_error(r##"struct Server { host, port }; json_parse("{\"host\": \"a\"}", Server)"##, "Missing field port for Server");
+    common::run_error(r##"struct Server { host, port }; let s = freeze(Server("a", 1)); s.port = 2"##, "Cannot modify a frozen Server");
+}
+
+#[test]
+fn test_null_safe_access() {
+    common::run(r##"
+    let config = {"name": "a", "nested": {"port": 80}, "list": [1, 2]}
+    print(config?.name, config?.missing, config?.nested?.port, config?.missing?.port)
+    "##, "a null 80 null");
+
+    common::run(r##"
+    let config = {"name": "a", "list": [1, 2]}
+    print(config?["name"], config.list?[5], config.list?[1], null?[1], null?[0..1])
+    "##, "a null 2 null null");
+
+    common::run(r##"
+    let config = {"name": "a"}
+    print(config?.missing ?? "default", config.name ?? "x", null ?? null ?? 3)
+    let evaluated = 1 ?? print("not evaluated")
+    "##, "default a 3");
+
+    common::run(r##"
+    let config = {"name": "a", "list": [1, 2]}
+    print(get(config, "name", "d"), get(config, "x", "d"), get(config, "x"), get(config.list, 7, 0), get(null, "x", 1))
+    "##, "a d null 0 1");
+
+    common::run(r##"
+    print(null == null, null == 1, 1 != null, null, [null], !null)
+    "##, "1 0 1 null [null] 1");
+
+    common::run(r##"
+    let parsed = json_parse("{\"a\": null}")
+    print(parsed.a == null, json_stringify(parsed))
+    print(match parsed.a { null => "null", _ => "other" }, match [null] { [is Null] => "list of null", _ => "other" })
+    "##, "1 {\"a\": null}null list of null");
+
+    common::run_error(r##"
+    let n = 1
+    print(n?.name)
+    "##, "Trying to index into non-indexable type Number");
+
+    common::run_error(r##"
+    let config = {"name": "a"}
+    print(config.missing)
+    "##, "Identifier missing could not be resolved");
+}
+
+#[test]
+fn test_negative_indices_and_slices() {
+    common::run(r##"
+    let l = [1, 2, 3, 4, 5]
+    print(l[-1], l[-5], l[1..], l[..-1], l[..], l[-2..], l[1..3])
+    "##, "5 1 [2, 3, 4, 5] [1, 2, 3, 4] [1, 2, 3, 4, 5] [4, 5] [2, 3]");
+
+    common::run(r##"
+    let s = "héllo wörld"
+    print(s[1], s[-1], s[0..5], s[6..], s[..-3], s?[20], s?[-1])
+    "##, "é d héllo wörld héllo wö null d");
+
+    common::run(r##"
+    let l = [1, 2, 3]
+    let i = 1
+    print(l[i..i + 1], l[len(l) - 1], l[-i])
+    "##, "[2] 3 3");
+
+    common::run_error(r##"
+    let l = [1, 2, 3]
+    print(l[-4])
+    "##, "Index out of bounds. Value length is 3 index was -4");
+
+    common::run_error(r##"
+    print("abc"[1..5])
+    "##, "Slice variables out of bound");
+}
@@ -138,4 +138,335 @@ fn test_builtin() {
     }
     "##, "pass");
 
-}
\ No newline at end of file
+}
+#[test]
+fn test_arguments() {
+    common::run(r##"
+    function connect(host, port = 80, scheme = port == 443 && "https" || "http") {
+        return scheme + "://" + host + ":" + to_str(port)
+    }
+    println(connect("a"))
+    println(connect("a", 443))
+    println(connect("a", scheme: "ftp"))
+    println(connect(port: 8080, host: "b"))
+    "##, "http://a:80\nhttps://a:443\nftp://a:80\nhttp://b:8080\n");
+
+    common::run(r##"
+    function count(first, ...rest) { return [first, len(rest)] }
+    let sum = |...n| { let s = 0 for x in n { s += x } return s }
+    print(count(1), count(1, 2, 3), sum(), sum(1, 2, 3))
+    "##, "[1, 0] [1, 2] 0 6");
+
+    common::run(r##"
+    let t = { "greet": |name, greeting = "hello"| { return greeting + " " + name } }
+    print(t.greet("you", greeting: "hi"), join(["a", "b"]))
+    "##, "hi you ab");
+
+    common::run_error(r##"function f(x) { return x } f(y: 1)"##, "Function f has no parameter named y");
+    common::run_error(r##"function f(x) { return x } f(1, x: 1)"##, "Parameter x of function f is given more than once");
+    common::run_error(r##"function f(x, y) { return x } f(1)"##, "Missing argument y for function call f");
+    common::run_error(r##"function f(x, y = 1) { return x } f(y: 1, 2)"##, "Named arguments must come after all positional arguments");
+    common::run_error(r##"function f(...x, y) { return x }"##, "The rest parameter must be the last parameter");
+    common::run_error(r##"print("a", end: "")"##, "Builtin function print does not accept named arguments");
+    common::run_error(r##"join()"##, "Expected 1 to 2 arguments, but got 0");
+}
+
+#[test]
+fn test_higher_order() {
+    common::run(r##"
+    let double = |x| x * 2
+    let l = [3, 1, 2]
+    println(double(4), map(l, double), map(l, |x| x + 1), filter(l, |x| x > 1))
+    println(reduce(l, |a, b| a + b), reduce(l, |a, b| a + to_str(b), ""))
+    println(any(l, |x| x == 2), all(l, |x| x > 1), find(l, |x| x < 3), find(l, |x| x > 5, 0))
+    println(sort_by(l, |x| x), sort_by(["bb", "a", "ccc"], |s| -len(s)), map(l, to_str))
+    println(flat_map(l, |x| [x, x]), group_by([1, 2, 3, 4], |x| x % 2 == 0 && "even" || "odd").odd)
+    "##, "8 [6, 2, 4] [4, 2, 3] [3, 2]\n6 312\n1 0 1 0\n[1, 2, 3] [\"ccc\", \"bb\", \"a\"] [\"3\", \"1\", \"2\"]\n[3, 3, 1, 1, 2, 2] [1, 3]\n");
+
+    common::run(r##"
+    let people = [{ "name": "b", "age": 30 }, { "name": "a", "age": 20 }]
+    print(map(sort_by(people, |p| p.age), |p| p.name), people.map(|p| { return p.age }))
+    "##, "[\"a\", \"b\"] [30, 20]");
+
+    common::run_error(r##"map([1], 1)"##, "Expected value to be of type Function but it was of type Number");
+    common::run_error(r##"reduce([], |a, b| a)"##, "Cannot reduce an empty list without an initial value");
+    common::run_error(r##"find([1], |x| x > 1)"##, "No element in the list matched");
+    common::run_error(r##"sort_by([1, 2, 3], |x| x == 2 && acos(2) || x)"##, "Expected a key that can be sorted, but got");
+    common::run_error(r##"sort_by([1, 2], |x| [x, acos(2)])"##, "Expected a key that can be sorted");
+    common::run_error(r##"sort_by(["a", 1, "b"], |x| x)"##, "Expected keys of the same type, but got a String and a Number");
+    common::run_error(r##"sort_by([[1], ["a"]], |x| x)"##, "Type mismatch in comparison");
+}
+
+#[test]
+fn test_generators() {
+    common::run(r##"
+    function naturals() {
+        let n = 0
+        while 1 {
+            yield n
+            n += 1
+        }
+    }
+    println(collect(take(naturals(), 5)))
+    println(collect(take(skip(naturals(), 3), 2)), collect(zip(naturals(), ["a", "b"])))
+    for [i, v] in enumerate(chain(take(naturals(), 2), "xy")) { print(i, v, "") }
+    "##, "[0, 1, 2, 3, 4]\n[3, 4] [[0, \"a\"], [1, \"b\"]]\n0 0 1 1 2 x 3 y ");
+
+    common::run(r##"
+    function walk(tree) {
+        if is_list(tree) {
+            for child in tree {
+                for leaf in walk(child) { yield leaf }
+            }
+        } else yield tree
+    }
+    function evens(limit) {
+        for i = 0; i < limit; i += 1 {
+            match i % 2 {
+                0 => { yield i }
+                _ => { continue }
+            }
+            if i >= 4 { return 0 }
+        }
+    }
+    print(collect(walk([1, [2, [3, 4]], 5])), collect(evens(100)))
+    "##, "[1, 2, 3, 4, 5] [0, 2, 4]");
+
+    common::run(r##"
+    let count = 0
+    let g = |n| { for i in 0..n { count += 1 yield i * i } }
+    let squares = g(8)
+    for s in squares { if s > 10 { break } }
+    println(count, collect(take(squares, 2)), collect(squares), count)
+    "##, "5 [25, 36] [49] 8\n");
+
+    common::run(r##"
+    echo "a\nb" $> out
+    for i, line in out { print(i, line, "") }
+    "##, "0 a 1 b ");
+
+    common::run_error(r##"yield 1"##, "yield can only be used in the body of a function");
+    common::run_error(r##"function g() { yield 1 } take(g(), -1)"##, "Expected a count that is not negative, but got -1");
+}
+
+#[test]
+fn test_type_annotations() {
+    common::run(r##"
+    struct Point { x, y }
+    function f(name: String, n: Number = 2, ...rest: Number): List {
+        return [name, n, len(rest)]
+    }
+    let l: List = f("a", 1, 2, 3)
+    let p: Point = Point(1, 2)
+    let g: Function = |x: Any| x
+    print(l, p.x, g(3))
+    "##, "[\"a\", 1, 2] 1 3");
+
+    common::run_error(r##"
+    let x: Number = "a"
+    "##, "Expected x to be a Number, but it is a String");
+
+    common::run_error(r##"
+    function f(x: String) { return x }
+    f(1)
+    "##, "Expected argument x of function f to be a String, but it is a Number");
+
+    common::run_error(r##"
+    function f(...rest: Number) { return rest }
+    f(1, "2")
+    "##, "Expected argument rest of function f to be a Number, but it is a String");
+
+    common::run_error(r##"
+    function f(x): Number { return "a" }
+    f(1)
+    "##, "Expected the return value of function f to be a Number, but it is a String");
+
+    common::run_error(r##"
+    function f(x): Number { }
+    f(1)
+    "##, "Expected function f to return a Number, but it returned no value");
+}
+
+#[test]
+fn test_unicode_builtins() {
+    common::run(r##"
+    print(len("åæø"), chars("åæø"), len(graphemes("e\u{301}a")), len("e\u{301}a"))
+    "##, "3 [\"å\", \"æ\", \"ø\"] 2 3");
+
+    common::run(r##"
+    print(substring("héllo", 1, 3), substring("héllo", -2), upper("straße"), lower("ÅÆ"), "héllo".upper())
+    "##, "él lo STRASSE åæ HÉLLO");
+
+    common::run(r##"
+    print("[" + pad_left("ab", 5) + "]", "[" + pad_right("日本", 6, ".") + "]", display_width("日本"), pad_left("long", 2))
+    "##, "[   ab] [日本..] 4 long");
+
+    common::run(r##"
+    print(reverse("ñandu\u{301}"), reverse([1, 2, 3]))
+    "##, "u\u{301}dnañ [3, 2, 1]");
+
+    common::run_error(r##"
+    pad_left("a", 3, "ab")
+    "##, "Expected a single fill character, but got \"ab\"");
+
+    common::run_error(r##"
+    pad_left("ab", 10^19)
+    "##, "the result is too long");
+}
+
+#[test]
+fn test_string_builtins() {
+    common::run(r##"
+    print(ends_with("abc", "bc"), contains("héllo", "ll"), contains([1, "a"], "a"), index_of("héllo", "l"), index_of([1, 2], 3))
+    "##, "1 1 1 2 -1");
+
+    common::run(r##"
+    print(replace("a-b-c", "-", "+"), replace_all("a-b-c", "-", "+"), repeat("ab", 3), lines("a\nb\r\nc\n"), split_whitespace("  a b\t c "))
+    "##, "a+b-c a+b+c ababab [\"a\", \"b\", \"c\"] [\"a\", \"b\", \"c\"]");
+
+    common::run(r##"
+    print("[" + trim("xxaxx", "x") + "]", "[" + trim_start("  a  ") + "]", "[" + trim_end("a..!", ".!") + "]", "[" + trim(" a ") + "]")
+    "##, "[a] [a  ] [a] [a]");
+
+    common::run_error(r##"
+    repeat("ab", 10^19)
+    "##, "Cannot repeat a string 10000000000000000000 times, the result is too long");
+}
+
+#[test]
+fn test_format() {
+    common::run(r##"
+    print(format("{} is {:>6.2} {:<4}| {:^7}|{:*^5}|{1}|{{x}}", "pi", 3.14159, "ab", "mid", 1))
+    "##, "pi is   3.14 ab  |   mid  |**1**|3.14159|{x}");
+
+    common::run(r##"
+    print(format("{:05.1}|{:04}|{:>4}|{:4}|{:.2}|{:3}", -2.5, 7, "日本", 7, "héllo", [1]))
+    "##, "-02.5|0007|日本|   7|hé|[1]");
+
+    common::run_error(r##"
+    format("{} {}", 1)
+    "##, "Missing argument 1 for format, got 1 arguments");
+
+    common::run_error(r##"
+    format("{:x}", 1)
+    "##, "Invalid format specifier x");
+
+    common::run_error(r##"
+    format("{", 1)
+    "##, "Unclosed { in format template");
+
+    common::run_error(r##"
+    format("{:.70000}", 1.5)
+    "##, "Expected a precision of at most 65535, but got 70000");
+
+    common::run_error(r##"
+    format("{:10000000000000000000}", 1)
+    "##, "the result is too long");
+}
+
+#[test]
+fn test_regex() {
+    common::run(r##"
+    let version = re"(?P<major>\d+)\.(?P<minor>\d+)(\.(\d+))?"
+    print(re_match(version, "v1.2"), re_match("^a", "ba"), version)
+    "##, "1 0 (?P<major>\\d+)\\.(?P<minor>\\d+)(\\.(\\d+))?");
+
+    common::run(r##"
+    print(re_find_all(re"\d+", "a1b22c333"), re_split(re"\s*,\s*", "a , b,c"))
+    "##, "[\"1\", \"22\", \"333\"] [\"a\", \"b\", \"c\"]");
+
+    common::run(r##"
+    let version = re"(?P<major>\d+)\.(?P<minor>\d+)(\.(\d+))?"
+    print(re_captures(version, "v1.20"), re_captures(version, "none"))
+    "##, "{\"0\": \"1.20\", \"major\": \"1\", \"minor\": \"20\", \"3\": null, \"4\": null} null");
+
+    common::run(r##"
+    print(re_replace(re"\d+", "a1b22", "<$0>"), re_replace(re"(?P<n>\d+)", "a1b22", |c| to_str(parse_number(c.n) * 2)))
+    "##, "a<1>b<22> a2b44");
+
+    common::run(r##"
+    let vowel = re_compile("[aeiou]")
+    let count = 0
+    for word in ["apple", "sky", "tree"] { if re_match(re"[aeiou]", word) && vowel.re_match(word) { count += 1 } }
+    print(count, match vowel { is Regex => "regex", _ => "other" }, re"a" == re"a", same(vowel, vowel))
+    "##, "2 regex 1 1");
+
+    common::run_error(r##"
+    re_compile("(")
+    "##, "Invalid regular expression");
+
+    common::run_error(r##"
+    re_replace(re"a", "a", |c| 1)
+    "##, "Expected value to be of type String but it was of type Number");
+}
+
+#[test]
+fn test_math_builtins() {
+    common::run(r##"
+    print(floor(1.7), ceil(1.2), floor(-1.5), abs(-3), sqrt(16), log(100, 10), round(log(8, 2)), log(1))
+    "##, "1 2 -2 3 4 2 3 0");
+
+    common::run(r##"
+    print(round(3.14159, 2), round(1234.5, -2), round(2.5), min(3, 1, 2), max([4, 9, 2]), min("b", "a"), clamp(15, 0, 10))
+    "##, "3.14 1200 3 1 9 a 10");
+
+    common::run(r##"
+    print(round(1.5, 400), round(1.5, -400))
+    "##, "1.5 1.5");
+
+    common::run(r##"
+    print(sin(0), cos(0), round(atan2(1, 1) * 4, 5), round(acos(-1), 5))
+    "##, "0 1 3.14159 3.14159");
+
+    common::run(r##"
+    print(parse_int("ff", 16), parse_int("-101", 2), parse_int(" 42 "))
+    "##, "255 -5 42");
+
+    common::run(r##"
+    print(format_number(1234567.891, 2), format_number(-1234), format_number(999), format_number(1234567, 0, "."), format_number(0.5))
+    "##, "1,234,567.89 -1,234 999 1.234.567 0.5");
+
+    common::run(r##"
+    random_seed(42)
+    let first = [random(), random_int(1, 6)]
+    random_seed(42)
+    let second = [random(), random_int(1, 6)]
+    print(first == second, first[0] >= 0 && first[0] < 1, first[1] >= 1 && first[1] <= 6)
+    "##, "1 1 1");
+
+    common::run_error(r##"
+    min([acos(2), 1])
+    "##, "Expected values that can be compared, but got NaN");
+
+    common::run_error(r##"
+    max(1, acos(2))
+    "##, "Expected values that can be compared, but got NaN");
+
+    common::run_error(r##"
+    format_number(1.5, 70000)
+    "##, "Expected at most 65535 decimals, but got 70000");
+
+    common::run_error(r##"
+    clamp(1, acos(2), 2)
+    "##, "Expected the bounds of clamp to be numbers, but got NaN");
+
+    common::run_error(r##"
+    sqrt(-1)
+    "##, "Cannot take the square root of the negative number -1");
+
+    common::run_error(r##"
+    parse_int("12", 1)
+    "##, "Expected a radix from 2 to 36, but got 1");
+
+    common::run_error(r##"
+    parse_int("1z")
+    "##, "Parse error for value 1z with radix 10");
+
+    common::run_error(r##"
+    min([])
+    "##, "Expected at least one value");
+
+    common::run_error(r##"
+    clamp(1, 10, 0)
+    "##, "Expected the lower bound 10 to not be greater than the upper bound 0");
+}
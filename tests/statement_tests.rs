@@ -129,6 +129,22 @@ fn test_match() {
       }
     }"##,"pass");
     common::run(r##"
+    match 3 {
+      1 -> 5 => { print("pass") }
+      3 => { print("fail") }
+      _ => { print("fail") }
+    }"##,"pass");
+    common::run(r##"
+    function f(x) {
+      match x {
+        1 => { return "one" }
+        _ => { return "other" }
+      }
+      return "fail"
+    }
+    print(f(1), f(2))
+    "##,"one other");
+    common::run(r##"
     match -7 {
       -20 -> -5; 7 => {
       print("pass")
@@ -173,4 +189,376 @@ fn test_white_space() {
     )
     "##,"pass");
 
-}
\ No newline at end of file
+}
+#[test]
+fn test_compound_assignment() {
+    common::run(r##"
+    let i = 1
+    i += 4
+    i -= 1
+    i *= 3
+    i /= 2
+    i %= 4
+    print(i)
+    "##,"2");
+
+    common::run(r##"
+    let s = "pa"
+    s += "ss"
+    print(s)
+    "##,"pass");
+
+    common::run(r##"
+    let l = [1, 2]
+    l[1] += 40
+    let t = { "a": 1 }
+    t["a"] *= 10
+    t.a += 2
+    print(l[1], t.a)
+    "##,"42 12");
+
+    common::run(r##"
+    let s = ""
+    for i=0;i<6;i+=2 {
+        s += to_str(i)
+    }
+    print(s)
+    "##,"024");
+}
+
+#[test]
+fn test_nested_assignment() {
+    common::run(r##"
+    let config = { "servers": [ { "port": 80 } ] }
+    config.servers[0].port = 8080
+    config.servers[0]["host"] = "localhost"
+    print(config.servers[0]["port"], config.servers[0]["host"])
+    "##,"8080 localhost");
+
+    common::run(r##"
+    let m = { "a": {} }
+    m["a"]["b"] = 1
+    m["a"]["b"] += 41
+    print(m["a"]["b"])
+    "##,"42");
+
+    common::run(r##"
+    let m = {}
+    auto m.a["b"].c = "pass"
+    auto m.a["b"].d = "pass"
+    print(m.a.b.c, len(m.a.b))
+    "##,"pass 2");
+
+    common::run(r##"
+    let auto = [0, { "x": 1 }]
+    auto[0] = 1
+    auto[1].x = 2
+    auto = auto[0] + auto[1].x
+    print(auto)
+    "##,"3");
+
+    common::run(r##"
+    let l = [1]
+    l[] = 2
+    let t = { "l": [] }
+    t.l[] = "pass"
+    print(l, t.l[0])
+    "##,"[1, 2] pass");
+}
+
+#[test]
+fn test_destructuring() {
+    common::run(r##"
+    function pair() { return ["pa", "ss"] }
+    let [a, b] = pair()
+    print(a + b)
+    "##,"pass");
+
+    common::run(r##"
+    let cfg = { "name": "srv", "port": 80, "opts": { "tls": 1 } }
+    let {name, port, opts: {tls: secure}} = cfg
+    print(name, port, secure)
+    "##,"srv 80 1");
+
+    common::run(r##"
+    let [x, [y, z]] = [1, [2, 3]]
+    print(x + y + z)
+    "##,"6");
+
+    common::run(r##"
+    let s = ""
+    for [k, v] in entries({ "a": 1 }) {
+        s = s + k + to_str(v)
+    }
+    for {n} in [{ "n": "b" }, { "n": "c" }] {
+        s = s + n
+    }
+    print(s)
+    "##,"a1bc");
+
+    common::run_error("let [a, b, c] = [1, 2]", "List has no element at index 2 for c");
+    common::run_error("let [a] = [1, 2]", "List pattern expects 1 elements, but the list has 2");
+    common::run_error(r##"let {name, port} = { "name": "x" }"##, "Key port not found in table");
+    common::run_error("let {a} = [1]", "Cannot destructure a List with a table pattern");
+}
+
+#[test]
+fn test_match_patterns() {
+    common::run(r##"
+    match ["run", "fast", "now"] {
+      ["stop"] => { print("fail") }
+      ["run", ...rest] => { print(join(rest, " ")) }
+    }"##,"fast now");
+
+    common::run(r##"
+    match [1, 2, 3, 4] {
+      [first, ..., last] => { print(first, last) }
+    }"##,"1 4");
+
+    common::run(r##"
+    match { "status": "ok", "data": { "id": 7 } } {
+      { "status": "error" } => { print("fail") }
+      { status: "ok", data: {id} } => { print("pass", id) }
+    }"##,"pass 7");
+
+    common::run(r##"
+    function describe(v) {
+      match v {
+        is String s => { print("string " + s + ";") }
+        is Number n if n > 10 => { print("big;") }
+        is Number n if n <= 10 => { print("number;") }
+        is List => { print("list;") }
+      }
+    }
+    describe("x")
+    describe(42)
+    describe(3)
+    describe([])
+    "##,"string x;big;number;list;");
+
+    common::run(r##"
+    struct Point { x, y }
+    let issue = 5
+    function describe(v) {
+      match v {
+        is Point p => { print("point", p.x) }
+        issue => { print("issue") }
+        is Any a => { print("any", a) }
+      }
+    }
+    describe(Point(1, 2))
+    describe(5)
+    describe("x")
+    "##,"point 1issueany x");
+
+    common::run(r##"
+    match "key=value" {
+      re"^(?P<k>\w+)=(?P<v>.*)$" => { print(k, v) }
+      re"^\d" => { print("fail") }
+    }"##,"key value");
+
+    common::run(r##"
+    match "no match" {
+      re"^\d+$" => { print("fail") }
+      1 -> 5 => { print("fail") }
+      "no match" if 0 => { print("fail") }
+      _ => { print("pass") }
+    }"##,"pass");
+
+    common::run_error(r##"
+    match [1] {
+      [...a, ...b] => { print("fail") }
+    }"##,"A list pattern can only contain one rest pattern");
+
+    common::run(r##"
+    let [head, ...tail] = [1, 2, 3]
+    print(head, tail)
+    "##,"1 [2, 3]");
+}
+
+#[test]
+fn test_for_in_iterables() {
+    common::run(r##"
+    let s = ""
+    for i in 0..4 { s += to_str(i) }
+    for i in 3..1 { s += "fail" }
+    let n = 2
+    for i, v in n..n + 2 { s += " " + to_str(i) + ":" + to_str(v) }
+    print(s)
+    "##, "0123 0:2 1:3");
+
+    common::run(r##"
+    let t = { "b": 2, "a": 1, "c": 3 }
+    for k, v in t { print(k + "=" + to_str(v) + " ") }
+    for k in t { print(k) }
+    "##, "b=2 a=1 c=3 bac");
+
+    common::run(r##"
+    for ch in "héj" { print("[" + ch + "]") }
+    for i, v in ["x", "y"] { print(i, v, "") }
+    "##, "[h][é][j]0 x 1 y ");
+
+    common::run_error(r##"for x in 5 { }"##, "Expected a List, Table, String, ProcessResult or Iterator to iterate over, but got a Number");
+}
+
+#[test]
+fn test_loop_labels() {
+    common::run(r##"
+    let s = ""
+    outer: for i in 0..3 {
+        for j in 0..3 {
+            if j == 1 { continue outer }
+            if i == 2 { break outer }
+            s += to_str(i) + to_str(j) + " "
+        }
+    }
+    print(s)
+    "##, "00 10 ");
+
+    common::run(r##"
+    let n = 0
+    rows: while n < 10 {
+        n += 1
+        for (i = 0; i < 5; i = i + 1) {
+            if n == 3 { break rows }
+            if i == 1 { break }
+        }
+    }
+    print(n)
+    "##, "3");
+
+    common::run(r##"
+    let i = 10
+    do {
+        print(i)
+        i += 1
+    } while i < 3
+    let j = 0
+    do { j += 1; if j == 2 { continue }; print(j) } while j < 4
+    "##, "10134");
+
+    common::run(r##"
+    function g() {
+        loop: for i in 0..3 {
+            for j in 0..3 {
+                if j > i { continue loop }
+                yield i * 10 + j
+            }
+        }
+    }
+    print(collect(g()))
+    "##, "[0, 10, 11, 20, 21, 22]");
+
+    common::run_error(r##"for i in 0..3 { break outer }"##, "No enclosing loop has the label outer");
+    common::run_error(r##"
+    function f() { continue outer }
+    outer: for i in 0..3 { f() }
+    "##, "No enclosing loop has the label outer");
+    common::run_error(r##"break"##, "Unexpected break");
+}
+
+#[test]
+fn test_constants() {
+    common::run(r##"
+    const limit = 3
+    const [a, b] = [1, 2]
+    { let limit = 4; print(limit) }
+    print(limit, a + b)
+    "##, "43 3");
+    common::run_error(r##"const limit = 3; limit = 4"##, "Cannot assign to constant limit");
+    common::run_error(r##"const limit = 3; limit += 1"##, "Cannot assign to constant limit");
+    common::run_error(r##"const limit = 3; const limit = 4"##, "Constant limit is already declared in this scope");
+    common::run_error(r##"const limit = 3; let [a, limit] = [1, 2]"##, "Constant limit is already declared in this scope");
+    common::run_error(r##"const [a, a] = [1, 2]"##, "Constant a is already declared in this scope");
+    common::run_error(r##"const limit = 3; export limit = 4"##, "Constant limit is already declared in this scope");
+    common::run_error(r##"const limit = 3; function limit() { }"##, "Constant limit is already declared in this scope");
+    common::run(r##"
+    for i in [1, 2] { const c = i * 10; print(c) }
+    let n = 0
+    while n < 2 { const d = n; n += 1; print(d) }
+    "##, "102001");
+
+    common::run_error(r##"print = 1"##, "Cannot assign to the builtin function print");
+    common::run_error(r##"{ len = 1 }"##, "Cannot assign to the builtin function len");
+    common::run(r##"
+    function f(len) { return len * 2 }
+    for map in [1] { print(map) }
+    let max = 0
+    max = 5
+    let [split, x] = [[1, 2], 3]
+    function len(x) { return 42 }
+    print(f(2), max, split, x, len("abc"))
+    "##, "14 5 [1, 2] 3 42");
+    common::run(r##"
+    let shadowed = 1
+    let constants = 2
+    print(shadowed + constants)
+    "##, "3");
+}
+
+#[test]
+fn test_freeze() {
+    common::run(r##"
+    let t = freeze({ "a": [1, 2], "b": { "c": 3 } })
+    let alias = t
+    print(t.a[1], t.b.c)
+    alias = 5
+    print(alias)
+    "##, "2 35");
+    common::run(r##"
+    let f = freeze([1, 2])
+    let x = [0] + f
+    let y = x + x
+    print(f, x, y)
+    "##, "[1, 2] [0, 1, 2] [0, 1, 2, 0, 1, 2]");
+    common::run_error(r##"let t = freeze({ "a": 1 }); t.a = 2"##, "Cannot modify a frozen Table");
+    common::run_error(r##"let t = freeze({ "a": [1, 2] }); t.a[0] = 2"##, "Cannot modify a frozen List");
+    common::run_error(r##"let l = [[1]]; freeze(l); l[0][] = 2"##, "Cannot modify a frozen List");
+    common::run_error(r##"let t = freeze({}); auto t.a.b = 2"##, "Cannot modify a frozen Table");
+    common::run_error(r##"let t = freeze({ "k": "abc" }); t.k[0] = "x""##, "Cannot modify a frozen Table");
+    common::run_error(r##"let l = freeze([1, 2]); l[0..1] = [3]"##, "Cannot modify a frozen List");
+    common::run(r##"
+    let l = [1]
+    l[] = l
+    freeze(l)
+    let c = copy(l)
+    c[] = 2
+    print(len(c), len(l))
+    "##, "3 2");
+}
+
+#[test]
+fn test_slice_assignment() {
+    common::run(r##"
+    let l = [1, 2, 3, 4, 5]
+    l[1..3] = ["a"]
+    l[-1] = 9
+    l[..0] = [0]
+    l[5..] = [6, 7]
+    print(l)
+    "##, "[0, 1, \"a\", 4, 9, 6, 7]");
+
+    common::run(r##"
+    let name = "hello"
+    name[0] = "J"
+    name[1..] += "!"
+    let t = {"k": "wörld"}
+    t.k[-1] = "D"
+    print(name, t.k)
+    "##, "Jello! wörlD");
+
+    common::run_error(r##"
+    let l = [1, 2]
+    l[0..1] = 3
+    "##, "Expected a List to assign to a slice, but got a Number");
+
+    common::run_error(r##"
+    let l = [1, 2]
+    l[0..1] += [3]
+    "##, "Only plain assignment is allowed when assigning to a slice");
+
+    common::run_error(r##"
+    const s = "abc"
+    s[0] = "x"
+    "##, "Cannot assign to constant s");
+}